@@ -19,7 +19,7 @@ fn main() {
         .border_color(Color::Green));
     
     // Emoji as bullet points in a list
-    let items = vec![
+    let items = [
         "🍎 Apple",
         "🍌 Banana",
         "🍇 Grapes",
@@ -55,7 +55,7 @@ fn main() {
         .border(BorderStyle::Double)
         .border_color(Color::IntenseBlue));
     
-    let dashboard = vec![
+    let dashboard = [
         "🔋 Battery: 85%",
         "💾 Disk: 120GB free",
         "🖥️ CPU: 12% usage",
@@ -1,6 +1,3 @@
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
 use termio::prelude::*;
 
 fn main() {
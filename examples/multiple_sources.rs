@@ -1,7 +1,4 @@
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
-use termio::{prelude::*, style};
+use termio::{prelude::*, style, EmbeddedResolver};
 
 fn main() {
     // Create a new Termio instance
@@ -33,28 +30,23 @@ fn main() {
     }
 
     // Load additional styles from file
-    let additional_styles_path = Path::new("examples/styles.tcss");
-    let mut additional_styles = String::new();
-
-    // Read the file
-    let mut file = match File::open(&additional_styles_path) {
-        Ok(file) => file,
+    match tcss.parse_file("examples/styles.tcss") {
+        Ok(_) => println!("Additional styles successfully loaded from file!"),
         Err(e) => {
-            eprintln!("Failed to open style file: {}", e);
+            eprintln!("Error parsing additional styles: {}", e);
             return;
         }
-    };
-
-    if let Err(e) = file.read_to_string(&mut additional_styles) {
-        eprintln!("Failed to read style file: {}", e);
-        return;
     }
 
-    // Parse additional styles
-    match tcss.parse(&additional_styles) {
-        Ok(_) => println!("Additional styles successfully loaded from file!"),
+    // Sheets can also pull in other sheets with `@import "styles.tcss";`,
+    // resolved relative to the importing file. Embedded sheets are served
+    // by an `EmbeddedResolver`.
+    let resolver = EmbeddedResolver::new().with("palette.tcss", include_str!("styles.tcss"));
+    let mut embedded = Termio::new().with_resolver(resolver);
+    match embedded.parse("@import \"palette.tcss\";") {
+        Ok(_) => println!("Embedded styles successfully imported!"),
         Err(e) => {
-            eprintln!("Error parsing additional styles: {}", e);
+            eprintln!("Error importing embedded styles: {}", e);
             return;
         }
     }
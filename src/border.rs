@@ -1,11 +1,135 @@
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum BorderStyle {
-    Solid,   // ┌─┐ │ └─┘
-    Dashed,  // ┌┈┐ ┊ └┈┘
-    Rounded, // ╭─╮ │ ╰─╯
-    Double,  // ╔═╗ ║ ╚═╝
+    Solid,            // ┌─┐ │ └─┘
+    Dashed,           // ┌┈┐ ┊ └┈┘
+    Rounded,          // ╭─╮ │ ╰─╯
+    Double,           // ╔═╗ ║ ╚═╝
+    Heavy,            // ┏━┓ ┃ ┗━┛
+    Ascii,            // +-+ | +-+
+    Block,            // ███ █ ███
+    DoubleHorizontal, // ╒═╕ │ ╘═╛
+}
+
+impl BorderStyle {
+    /// Returns the characters the border is drawn with.
+    pub fn chars(&self) -> BorderChars {
+        let chars = match self {
+            BorderStyle::Solid => "┌─┐│┘─└│",
+            BorderStyle::Dashed => "┌┈┐┊┘┈└┊",
+            BorderStyle::Rounded => "╭─╮│╯─╰│",
+            BorderStyle::Double => "╔═╗║╝═╚║",
+            BorderStyle::Heavy => "┏━┓┃┛━┗┃",
+            BorderStyle::Ascii => "+-+|+-+|",
+            BorderStyle::Block => "████████",
+            BorderStyle::DoubleHorizontal => "╒═╕│╛═╘│",
+        };
+        chars.parse().unwrap()
+    }
+}
+
+/// The characters a border is drawn with.
+///
+/// In TCSS they are written clockwise from the top left corner, as in
+/// `border-chars: "╭─╮│╯─╰│";`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BorderChars {
+    pub top_left: char,
+    pub top: char,
+    pub top_right: char,
+    pub right: char,
+    pub bottom_right: char,
+    pub bottom: char,
+    pub bottom_left: char,
+    pub left: char,
+}
+
+impl BorderChars {
+    /// Creates border characters with the same corners and lines on opposite sides.
+    pub fn new(corner: char, horizontal: char, vertical: char) -> Self {
+        BorderChars {
+            top_left: corner,
+            top: horizontal,
+            top_right: corner,
+            right: vertical,
+            bottom_right: corner,
+            bottom: horizontal,
+            bottom_left: corner,
+            left: vertical,
+        }
+    }
+
+    /// Replaces characters outside ASCII with the closest of `+`, `-`, `=`,
+    /// `|` and `:`, for terminals without Unicode box drawing.
+    pub(crate) fn to_ascii(self) -> BorderChars {
+        let corner = |c: char| if c.is_ascii() { c } else { '+' };
+        let horizontal = |c: char| match c {
+            c if c.is_ascii() => c,
+            '═' => '=',
+            _ => '-',
+        };
+        let vertical = |c: char| match c {
+            c if c.is_ascii() => c,
+            '┊' => ':',
+            _ => '|',
+        };
+        BorderChars {
+            top_left: corner(self.top_left),
+            top: horizontal(self.top),
+            top_right: corner(self.top_right),
+            right: vertical(self.right),
+            bottom_right: corner(self.bottom_right),
+            bottom: horizontal(self.bottom),
+            bottom_left: corner(self.bottom_left),
+            left: vertical(self.left),
+        }
+    }
+}
+
+impl FromStr for BorderChars {
+    type Err = String;
+
+    /// Parses eight characters, clockwise from the top left corner.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        match chars[..] {
+            [top_left, top, top_right, right, bottom_right, bottom, bottom_left, left] => {
+                Ok(BorderChars {
+                    top_left,
+                    top,
+                    top_right,
+                    right,
+                    bottom_right,
+                    bottom,
+                    bottom_left,
+                    left,
+                })
+            }
+            _ => Err(format!(
+                "Invalid border characters: {}. Use 8 characters, clockwise from the top left",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BorderChars {
+    /// Formats the characters clockwise from the top left, as accepted by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars = [
+            self.top_left,
+            self.top,
+            self.top_right,
+            self.right,
+            self.bottom_right,
+            self.bottom,
+            self.bottom_left,
+            self.left,
+        ];
+        write!(f, "{}", chars.iter().collect::<String>())
+    }
 }
 
 impl FromStr for BorderStyle {
@@ -17,7 +141,71 @@ impl FromStr for BorderStyle {
             "dashed" => Ok(BorderStyle::Dashed),
             "rounded" => Ok(BorderStyle::Rounded),
             "double" => Ok(BorderStyle::Double),
+            "heavy" => Ok(BorderStyle::Heavy),
+            "ascii" => Ok(BorderStyle::Ascii),
+            "block" => Ok(BorderStyle::Block),
+            "double-horizontal" => Ok(BorderStyle::DoubleHorizontal),
             _ => Err(format!("Unknown border style: {}", s)),
         }
     }
 }
+
+impl fmt::Display for BorderStyle {
+    /// Formats the border style in TCSS syntax, as accepted by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BorderStyle::Solid => "solid",
+            BorderStyle::Dashed => "dashed",
+            BorderStyle::Rounded => "rounded",
+            BorderStyle::Double => "double",
+            BorderStyle::Heavy => "heavy",
+            BorderStyle::Ascii => "ascii",
+            BorderStyle::Block => "block",
+            BorderStyle::DoubleHorizontal => "double-horizontal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BorderChars, BorderStyle};
+    use crate::capabilities::{Capabilities, ColorPolicy};
+    use crate::color::Color;
+    use crate::styled_text::StyledText;
+
+    #[test]
+    fn test_ascii_borders() {
+        let parser = crate::tcss! {
+            "boxed" => {
+                border_style: BorderStyle::Rounded
+            }
+        }
+        .with_capabilities(Capabilities::default().unicode(false))
+        .with_color_policy(ColorPolicy::Never);
+
+        let boxed = format!("{}", "Box".style("boxed", &parser));
+        assert_eq!(boxed, "+---+\n|Box|\n+---+");
+    }
+
+    #[test]
+    fn test_border_chars() {
+        let heavy = "Hi"
+            .border(BorderStyle::Heavy)
+            .color_policy(ColorPolicy::Never);
+        assert_eq!(format!("{}", heavy), "┏━━┓\n┃Hi┃\n┗━━┛");
+
+        let custom = "Hi"
+            .border(BorderStyle::Solid)
+            .border_chars("*=*!*~*!".parse().unwrap())
+            .color_policy(ColorPolicy::Never);
+        assert_eq!(format!("{}", custom), "*==*\n!Hi!\n*~~*");
+
+        let block = "Hi"
+            .border_color(Color::Red)
+            .border_chars(BorderChars::new('█', '█', '█'))
+            .capabilities(Capabilities::default().unicode(false))
+            .color_policy(ColorPolicy::Never);
+        assert_eq!(format!("{}", block), "+--+\n|Hi|\n+--+");
+    }
+}
@@ -0,0 +1,404 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// Number of colours a terminal can display.
+///
+/// Ordered from least to most capable, so depths can be compared directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+    NoColor,   // No escape codes at all
+    Ansi16,    // The 16 basic and intense colours
+    Ansi256,   // xterm 256-colour palette
+    TrueColor, // 24-bit RGB
+}
+
+impl ColorDepth {
+    /// Returns how many colours this depth can display.
+    pub fn colors(&self) -> u32 {
+        match self {
+            ColorDepth::NoColor => 0,
+            ColorDepth::Ansi16 => 16,
+            ColorDepth::Ansi256 => 256,
+            ColorDepth::TrueColor => 16_777_216,
+        }
+    }
+}
+
+/// When colors and other escape codes are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorPolicy {
+    /// Follow `NO_COLOR`, `CLICOLOR_FORCE`, `TERM=dumb`, `CLICOLOR` and
+    /// whether stdout is a terminal, in that order of precedence
+    #[default]
+    Auto,
+    /// Always write escape codes, downsampled to the colour depth
+    Always,
+    /// Never write escape codes
+    Never,
+}
+
+/// Output stream whose terminal decides `ColorPolicy::Auto`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl ColorPolicy {
+    /// Resolves `Auto` to `Always` or `Never` for output written to `stream`.
+    ///
+    /// The environment and the terminal are checked the first time a stream
+    /// is asked about and the answer is kept for the rest of the process.
+    /// Policies left on `Auto` follow stdout, so resolve them for stderr when
+    /// writing there.
+    pub fn resolve(self, stream: Stream) -> ColorPolicy {
+        static STDOUT: OnceLock<bool> = OnceLock::new();
+        static STDERR: OnceLock<bool> = OnceLock::new();
+
+        if self != ColorPolicy::Auto {
+            return self;
+        }
+        let enabled = match stream {
+            Stream::Stdout => STDOUT.get_or_init(|| {
+                auto_enabled(
+                    |name| std::env::var(name).ok(),
+                    std::io::stdout().is_terminal(),
+                )
+            }),
+            Stream::Stderr => STDERR.get_or_init(|| {
+                auto_enabled(
+                    |name| std::env::var(name).ok(),
+                    std::io::stderr().is_terminal(),
+                )
+            }),
+        };
+        if *enabled {
+            ColorPolicy::Always
+        } else {
+            ColorPolicy::Never
+        }
+    }
+
+    /// Returns the colour depth to render with on a terminal supporting `depth`.
+    pub fn color_depth(&self, depth: ColorDepth) -> ColorDepth {
+        match self.resolve(Stream::Stdout) {
+            ColorPolicy::Never => ColorDepth::NoColor,
+            _ => depth,
+        }
+    }
+}
+
+/// Decides `ColorPolicy::Auto` from environment variables and whether
+/// the output is a terminal.
+fn auto_enabled(var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+    let set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+    if set("NO_COLOR") {
+        return false;
+    }
+    if set("CLICOLOR_FORCE") && var("CLICOLOR_FORCE").as_deref() != Some("0") {
+        return true;
+    }
+    if var("TERM").as_deref() == Some("dumb") || var("CLICOLOR").as_deref() == Some("0") {
+        return false;
+    }
+    is_terminal
+}
+
+/// What the terminal the output is written to can do.
+///
+/// `@when` blocks in TCSS are evaluated against these values and styled
+/// strings are rendered with them. The default assumes a full-featured
+/// truecolor terminal of unknown size; [`detect`](Self::detect) inspects
+/// the actual one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    pub color_depth: ColorDepth, // Supported colour depth
+    pub width: Option<u16>,      // Terminal width in columns, if known
+    pub height: Option<u16>,     // Terminal height in rows, if known
+    pub unicode: bool,           // Unicode box-drawing characters display correctly
+    pub hyperlinks: bool,        // OSC 8 hyperlinks are supported
+    pub italics: bool,           // Italic text is supported
+    pub undercurl: bool,         // Curly, dotted and dashed underlines are supported
+}
+
+impl Capabilities {
+    /// Sets the colour depth.
+    pub fn color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = depth;
+        self
+    }
+
+    /// Sets the terminal size in columns and rows.
+    pub fn size(mut self, width: u16, height: u16) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Sets whether Unicode box-drawing characters display correctly.
+    pub fn unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Sets whether OSC 8 hyperlinks are supported.
+    pub fn hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Sets whether italic text is supported.
+    pub fn italics(mut self, italics: bool) -> Self {
+        self.italics = italics;
+        self
+    }
+
+    /// Sets whether curly, dotted and dashed underlines are supported.
+    pub fn undercurl(mut self, undercurl: bool) -> Self {
+        self.undercurl = undercurl;
+        self
+    }
+
+    /// Detects the capabilities of the terminal stdout is connected to.
+    ///
+    /// Unknown terminals get conservative values: 16 colours, no hyperlinks
+    /// and no extended underlines. The size is asked of the terminal on
+    /// Linux, macOS and the BSDs, and read from `COLUMNS` and `LINES`
+    /// elsewhere or when that fails.
+    pub fn detect() -> Self {
+        let mut capabilities = from_env(|name| std::env::var(name).ok());
+        if let Some((width, height)) = terminal_size() {
+            capabilities = capabilities.size(width, height);
+        }
+        capabilities
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            color_depth: ColorDepth::TrueColor,
+            width: None,
+            height: None,
+            unicode: true,
+            hyperlinks: true,
+            italics: true,
+            undercurl: true,
+        }
+    }
+}
+
+/// Derives the capabilities from environment variables; the size only
+/// from `COLUMNS` and `LINES`.
+fn from_env(var: impl Fn(&str) -> Option<String>) -> Capabilities {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    let vte = var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok());
+    let windows_terminal = var("WT_SESSION").is_some();
+    let modern = matches!(program.as_str(), "iTerm.app" | "WezTerm" | "vscode")
+        || ["kitty", "wezterm", "foot", "alacritty"]
+            .iter()
+            .any(|t| term.contains(t));
+    let legacy = term.is_empty()
+        || term == "dumb"
+        || term == "linux"
+        || term.starts_with("vt")
+        || term.starts_with("screen") && !term.contains("256color");
+
+    let color_depth = if term == "dumb" {
+        ColorDepth::NoColor
+    } else if colorterm == "truecolor" || colorterm == "24bit" || modern || windows_terminal {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") || program == "Apple_Terminal" {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    };
+
+    // The first locale variable that is set decides the character set
+    let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| var(name).filter(|v| !v.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        });
+
+    let mut capabilities = Capabilities::default()
+        .color_depth(color_depth)
+        .unicode((utf8 || windows_terminal) && !legacy)
+        .hyperlinks(
+            modern
+                || windows_terminal
+                || var("KONSOLE_VERSION").is_some()
+                || vte.is_some_and(|v| v >= 5000),
+        )
+        .italics(!legacy)
+        .undercurl(
+            modern && program != "vscode" && program != "iTerm.app"
+                || vte.is_some_and(|v| v >= 5102),
+        );
+    capabilities.width = var("COLUMNS").and_then(|v| v.parse().ok());
+    capabilities.height = var("LINES").and_then(|v| v.parse().ok());
+    capabilities
+}
+
+/// Asks the terminal on stdout, or else stderr, for its size in columns and rows.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+fn terminal_size() -> Option<(u16, u16)> {
+    use std::os::raw::c_int;
+
+    // musl and bionic declare the request as `int`, glibc and the BSDs as
+    // `unsigned long`
+    #[cfg(any(target_env = "musl", target_os = "android"))]
+    type Request = c_int;
+    #[cfg(not(any(target_env = "musl", target_os = "android")))]
+    type Request = std::os::raw::c_ulong;
+
+    #[repr(C)]
+    struct WinSize {
+        rows: u16,
+        cols: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+
+    extern "C" {
+        fn ioctl(fd: c_int, request: Request, ...) -> c_int;
+    }
+
+    // Linux uses its own numbering except on the architectures that
+    // inherited the BSD ioctl encoding
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "sparc",
+            target_arch = "sparc64"
+        ))
+    ))]
+    const TIOCGWINSZ: Request = 0x5413;
+    #[cfg(not(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "sparc",
+            target_arch = "sparc64"
+        ))
+    )))]
+    const TIOCGWINSZ: Request = 0x4008_7468;
+
+    [1, 2].into_iter().find_map(|fd| {
+        let mut size = WinSize {
+            rows: 0,
+            cols: 0,
+            x_pixels: 0,
+            y_pixels: 0,
+        };
+        // SAFETY: TIOCGWINSZ only writes a `winsize` struct, which `WinSize`
+        // mirrors, and fails harmlessly on descriptors that are not terminals
+        let result = unsafe { ioctl(fd, TIOCGWINSZ, &mut size as *mut WinSize) };
+        (result == 0 && size.cols > 0 && size.rows > 0).then_some((size.cols, size.rows))
+    })
+}
+
+/// Elsewhere the size comes from `COLUMNS` and `LINES` alone.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn terminal_size() -> Option<(u16, u16)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{auto_enabled, from_env, ColorDepth, ColorPolicy, Stream};
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_auto_color_policy() {
+        assert!(auto_enabled(env(&[]), true));
+        assert!(!auto_enabled(env(&[]), false));
+        assert!(!auto_enabled(env(&[("NO_COLOR", "1")]), true));
+        assert!(auto_enabled(env(&[("NO_COLOR", "")]), true));
+        assert!(auto_enabled(env(&[("CLICOLOR_FORCE", "1")]), false));
+        assert!(!auto_enabled(env(&[("CLICOLOR_FORCE", "0")]), false));
+        assert!(!auto_enabled(
+            env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]),
+            true
+        ));
+        assert!(!auto_enabled(env(&[("TERM", "dumb")]), true));
+        assert!(auto_enabled(
+            env(&[("TERM", "dumb"), ("CLICOLOR_FORCE", "1")]),
+            false
+        ));
+        assert!(!auto_enabled(env(&[("CLICOLOR", "0")]), true));
+    }
+
+    #[test]
+    fn test_resolve_color_policy() {
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            assert_eq!(ColorPolicy::Never.resolve(stream), ColorPolicy::Never);
+            assert_eq!(ColorPolicy::Always.resolve(stream), ColorPolicy::Always);
+            let auto = ColorPolicy::Auto.resolve(stream);
+            assert_ne!(auto, ColorPolicy::Auto);
+            assert_eq!(ColorPolicy::Auto.resolve(stream), auto);
+        }
+    }
+
+    #[test]
+    fn test_detect_from_env() {
+        let detect = |vars: &[(&str, &str)]| from_env(env(vars));
+        let kitty = detect(&[("TERM", "xterm-kitty"), ("LANG", "en_US.UTF-8")]);
+        assert_eq!(kitty.color_depth, ColorDepth::TrueColor);
+        assert!(kitty.unicode && kitty.hyperlinks && kitty.italics && kitty.undercurl);
+
+        let xterm = detect(&[
+            ("TERM", "xterm-256color"),
+            ("LANG", "C"),
+            ("COLUMNS", "100"),
+        ]);
+        assert_eq!(xterm.color_depth, ColorDepth::Ansi256);
+        assert!(!xterm.unicode && !xterm.hyperlinks && !xterm.undercurl);
+        assert_eq!(xterm.width, Some(100));
+
+        let colorterm = detect(&[("TERM", "xterm"), ("COLORTERM", "truecolor")]);
+        assert_eq!(colorterm.color_depth, ColorDepth::TrueColor);
+
+        let console = detect(&[("TERM", "linux"), ("LC_ALL", "en_US.UTF-8")]);
+        assert_eq!(console.color_depth, ColorDepth::Ansi16);
+        assert!(!console.unicode && !console.italics);
+
+        assert_eq!(detect(&[("TERM", "dumb")]).color_depth, ColorDepth::NoColor);
+    }
+}
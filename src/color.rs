@@ -1,3 +1,5 @@
+use crate::capabilities::ColorDepth;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -28,51 +30,165 @@ impl Color {
         Color::Rgb(r, g, b)
     }
 
+    /// Returns the RGB value of the color, using the xterm default palette
+    /// for the basic colors and color codes.
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Code(code) => xterm_rgb(code),
+            color => xterm_rgb(color.index().unwrap()),
+        }
+    }
+
+    /// Returns the xterm palette index of a basic color or color code.
+    pub(crate) fn index(&self) -> Option<u8> {
+        match *self {
+            Color::Rgb(..) => None,
+            Color::Code(code) => Some(code),
+            color => BASIC.iter().position(|c| *c == color).map(|i| i as u8),
+        }
+    }
+
+    /// Returns the basic color for indexes below 16, a color code otherwise.
+    pub(crate) fn from_index(index: u8) -> Color {
+        match BASIC.get(index as usize) {
+            Some(color) => *color,
+            None => Color::Code(index),
+        }
+    }
+
+    /// Increases the HSL lightness by `amount` (0.0 to 1.0).
+    pub fn lighten(&self, amount: f64) -> Color {
+        let (h, s, l) = rgb_to_hsl(self.to_rgb());
+        hsl_to_rgb(h, s, (l + amount).clamp(0.0, 1.0))
+    }
+
+    /// Decreases the HSL lightness by `amount` (0.0 to 1.0).
+    pub fn darken(&self, amount: f64) -> Color {
+        self.lighten(-amount)
+    }
+
+    /// Increases the HSL saturation by `amount` (0.0 to 1.0).
+    pub fn saturate(&self, amount: f64) -> Color {
+        let (h, s, l) = rgb_to_hsl(self.to_rgb());
+        hsl_to_rgb(h, (s + amount).clamp(0.0, 1.0), l)
+    }
+
+    /// Decreases the HSL saturation by `amount` (0.0 to 1.0).
+    pub fn desaturate(&self, amount: f64) -> Color {
+        self.saturate(-amount)
+    }
+
+    /// Blends with `other`; `ratio` is the share of `other`, from 0.0 to 1.0.
+    pub fn mix(&self, other: Color, ratio: f64) -> Color {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let (r1, g1, b1) = self.to_rgb();
+        let (r2, g2, b2) = other.to_rgb();
+        let blend = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * ratio).round() as u8;
+        Color::Rgb(blend(r1, r2), blend(g1, g2), blend(b1, b2))
+    }
+
+    /// Returns the RGB complement of the color.
+    pub fn invert(&self) -> Color {
+        let (r, g, b) = self.to_rgb();
+        Color::Rgb(255 - r, 255 - g, 255 - b)
+    }
+
+    /// Maps the color to the closest one the given depth can display.
+    ///
+    /// RGB colors become the nearest xterm-256 cube or grey code, or the
+    /// nearest of the 16 basic colors, by perceptual (CIELAB) distance.
+    /// Returns `None` when the depth has no colors at all.
+    pub fn downsample(&self, depth: ColorDepth) -> Option<Color> {
+        match (depth, *self) {
+            (ColorDepth::NoColor, _) => None,
+            (ColorDepth::TrueColor, color) => Some(color),
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => {
+                Some(Color::Code(nearest(16..=255, (r, g, b))))
+            }
+            (ColorDepth::Ansi256, color) => Some(color),
+            (ColorDepth::Ansi16, Color::Code(code)) if code < 16 => Some(BASIC[code as usize]),
+            (ColorDepth::Ansi16, Color::Code(code)) => {
+                Some(BASIC[nearest(0..=15, xterm_rgb(code)) as usize])
+            }
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => {
+                Some(BASIC[nearest(0..=15, (r, g, b)) as usize])
+            }
+            (ColorDepth::Ansi16, color) => Some(color),
+        }
+    }
+
     /// Converts the color to an ANSI foreground color code
     pub fn to_ansi_foreground(&self) -> String {
-        match self {
-            Color::Black => "\x1b[30m".to_string(),
-            Color::Red => "\x1b[31m".to_string(),
-            Color::Green => "\x1b[32m".to_string(),
-            Color::Yellow => "\x1b[33m".to_string(),
-            Color::Blue => "\x1b[34m".to_string(),
-            Color::Magenta => "\x1b[35m".to_string(),
-            Color::Cyan => "\x1b[36m".to_string(),
-            Color::White => "\x1b[37m".to_string(),
-            Color::IntenseBlack => "\x1b[90m".to_string(),
-            Color::IntenseRed => "\x1b[91m".to_string(),
-            Color::IntenseGreen => "\x1b[92m".to_string(),
-            Color::IntenseYellow => "\x1b[93m".to_string(),
-            Color::IntenseBlue => "\x1b[94m".to_string(),
-            Color::IntenseMagenta => "\x1b[95m".to_string(),
-            Color::IntenseCyan => "\x1b[96m".to_string(),
-            Color::IntenseWhite => "\x1b[97m".to_string(),
-            Color::Code(u) => format!("\x1b[38;5;{u}m"),
-            Color::Rgb(r, g, b) => format!("\x1b[38;2;{};{};{}m", r, g, b),
-        }
+        format!("\x1b[{}m", self.sgr_foreground())
+    }
+
+    /// Converts the color to an ANSI underline color code
+    ///
+    /// There are no short codes for the basic colors, so they use their
+    /// 256-color index.
+    pub fn to_ansi_underline(&self) -> String {
+        format!("\x1b[{}m", self.sgr_underline())
     }
 
     /// Converts the color to an ANSI background color code
     pub fn to_ansi_background(&self) -> String {
+        format!("\x1b[{}m", self.sgr_background())
+    }
+
+    /// Returns the SGR parameters setting the foreground color
+    pub(crate) fn sgr_foreground(&self) -> String {
+        match self {
+            Color::Black => "30".to_string(),
+            Color::Red => "31".to_string(),
+            Color::Green => "32".to_string(),
+            Color::Yellow => "33".to_string(),
+            Color::Blue => "34".to_string(),
+            Color::Magenta => "35".to_string(),
+            Color::Cyan => "36".to_string(),
+            Color::White => "37".to_string(),
+            Color::IntenseBlack => "90".to_string(),
+            Color::IntenseRed => "91".to_string(),
+            Color::IntenseGreen => "92".to_string(),
+            Color::IntenseYellow => "93".to_string(),
+            Color::IntenseBlue => "94".to_string(),
+            Color::IntenseMagenta => "95".to_string(),
+            Color::IntenseCyan => "96".to_string(),
+            Color::IntenseWhite => "97".to_string(),
+            Color::Code(u) => format!("38;5;{u}"),
+            Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+        }
+    }
+
+    /// Returns the SGR parameters setting the underline color
+    pub(crate) fn sgr_underline(&self) -> String {
         match self {
-            Color::Black => "\x1b[40m".to_string(),
-            Color::Red => "\x1b[41m".to_string(),
-            Color::Green => "\x1b[42m".to_string(),
-            Color::Yellow => "\x1b[43m".to_string(),
-            Color::Blue => "\x1b[44m".to_string(),
-            Color::Magenta => "\x1b[45m".to_string(),
-            Color::Cyan => "\x1b[46m".to_string(),
-            Color::White => "\x1b[47m".to_string(),
-            Color::IntenseBlack => "\x1b[100m".to_string(),
-            Color::IntenseRed => "\x1b[101m".to_string(),
-            Color::IntenseGreen => "\x1b[102m".to_string(),
-            Color::IntenseYellow => "\x1b[103m".to_string(),
-            Color::IntenseBlue => "\x1b[104m".to_string(),
-            Color::IntenseMagenta => "\x1b[105m".to_string(),
-            Color::IntenseCyan => "\x1b[106m".to_string(),
-            Color::IntenseWhite => "\x1b[107m".to_string(),
-            Color::Code(u) => format!("\x1b[48;5;{u}m"),
-            Color::Rgb(r, g, b) => format!("\x1b[48;2;{};{};{}m", r, g, b),
+            Color::Rgb(r, g, b) => format!("58;2;{};{};{}", r, g, b),
+            color => format!("58;5;{}", color.index().unwrap()),
+        }
+    }
+
+    /// Returns the SGR parameters setting the background color
+    pub(crate) fn sgr_background(&self) -> String {
+        match self {
+            Color::Black => "40".to_string(),
+            Color::Red => "41".to_string(),
+            Color::Green => "42".to_string(),
+            Color::Yellow => "43".to_string(),
+            Color::Blue => "44".to_string(),
+            Color::Magenta => "45".to_string(),
+            Color::Cyan => "46".to_string(),
+            Color::White => "47".to_string(),
+            Color::IntenseBlack => "100".to_string(),
+            Color::IntenseRed => "101".to_string(),
+            Color::IntenseGreen => "102".to_string(),
+            Color::IntenseYellow => "103".to_string(),
+            Color::IntenseBlue => "104".to_string(),
+            Color::IntenseMagenta => "105".to_string(),
+            Color::IntenseCyan => "106".to_string(),
+            Color::IntenseWhite => "107".to_string(),
+            Color::Code(u) => format!("48;5;{u}"),
+            Color::Rgb(r, g, b) => format!("48;2;{};{};{}", r, g, b),
         }
     }
 }
@@ -81,10 +197,20 @@ impl FromStr for Color {
     type Err = String;
 
     /// Parses a string into a Color variant.
-    /// Supports color names (e.g., "black", "i-red"), and RGB (e.g., "rgb(255, 0, 0)").
+    /// Supports color names (e.g., "black", "i-red"), 8-bit codes (e.g., "196"),
+    /// hex (e.g., "#ff8800", "#f80"), "rgb(255, 0, 0)", "hsl(30, 100%, 50%)"
+    /// CSS color names (e.g., "rebeccapurple") and the functions
+    /// "lighten", "darken", "saturate", "desaturate" (e.g., "darken(red, 20%)"),
+    /// "mix" (e.g., "mix(red, blue, 50%)") and "invert" (e.g., "invert(white)").
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Errors quote the input as written, `value` is what gets parsed
+        let value = s.trim();
+        if value.is_empty() {
+            return Err("Missing color value".to_string());
+        }
+        let lower = value.to_lowercase();
         // Handle named colors
-        match s.to_lowercase().as_str() {
+        match lower.as_str() {
             "black" => Ok(Color::Black),
             "red" => Ok(Color::Red),
             "green" => Ok(Color::Green),
@@ -102,29 +228,601 @@ impl FromStr for Color {
             "i-cyan" | "intense-cyan" => Ok(Color::IntenseCyan),
             "i-white" | "intense-white" => Ok(Color::IntenseWhite),
             _ => {
-                // Handle RGB colors
-                if s.starts_with("rgb(") && s.ends_with(")") {
-                    let rgb_str = &s[4..s.len() - 1];
-                    let parts: Vec<&str> = rgb_str.split(',').map(|s| s.trim()).collect();
-                    if parts.len() == 3 {
-                        let r = parts[0]
-                            .parse::<u8>()
-                            .map_err(|_| "Invalid RGB value".to_string())?;
-                        let g = parts[1]
-                            .parse::<u8>()
-                            .map_err(|_| "Invalid RGB value".to_string())?;
-                        let b = parts[2]
-                            .parse::<u8>()
-                            .map_err(|_| "Invalid RGB value".to_string())?;
-                        return Ok(Color::Rgb(r, g, b));
-                    }
+                if let Some(hex) = value.strip_prefix('#') {
+                    return parse_hex(hex).ok_or_else(|| {
+                        format!(
+                            "Invalid hex color: {}. Expected #rgb or #rrggbb with hex digits",
+                            s
+                        )
+                    });
+                }
+                if let Some(args) = function_args(value, "rgb") {
+                    let [r, g, b] = split_args(s, args)?;
+                    let channel = |v: &str| {
+                        v.parse::<u8>().map_err(|_| {
+                            format!("Invalid RGB value: {}. Expected 0 to 255 in {}", v, s)
+                        })
+                    };
+                    return Ok(Color::Rgb(channel(r)?, channel(g)?, channel(b)?));
+                }
+                if let Some(args) = function_args(value, "hsl") {
+                    let [h, sat, l] = split_args(s, args)?;
+                    let hue = h
+                        .strip_suffix("deg")
+                        .unwrap_or(h)
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|h| h.is_finite())
+                        .ok_or_else(|| format!("Invalid hue: {}. Expected degrees in {}", h, s))?;
+                    let percent = |name: &str, v: &str| {
+                        v.strip_suffix('%')
+                            .and_then(|v| v.trim().parse::<f64>().ok())
+                            .filter(|v| (0.0..=100.0).contains(v))
+                            .ok_or_else(|| {
+                                format!("Invalid {}: {}. Expected 0% to 100% in {}", name, v, s)
+                            })
+                    };
+                    let sat = percent("saturation", sat)?;
+                    let l = percent("lightness", l)?;
+                    return Ok(hsl_to_rgb(hue, sat / 100.0, l / 100.0));
+                }
+                if let Some(result) = parse_function(s, value) {
+                    return result;
                 }
                 // Handle 8-bit color codes
-                if let Ok(code) = s.parse::<u8>() {
-                    return Ok(Color::Code(code));
+                if value.bytes().all(|b| b.is_ascii_digit()) {
+                    return value
+                        .parse::<u8>()
+                        .map(Color::Code)
+                        .map_err(|_| format!("Invalid color code: {}. Expected 0 to 255", s));
+                }
+                if let Some(&(_, (r, g, b))) = CSS_COLORS.iter().find(|(name, _)| *name == lower) {
+                    return Ok(Color::Rgb(r, g, b));
                 }
                 Err(format!("Unknown color: {}", s))
             }
         }
     }
 }
+
+/// The 16 basic colors in xterm code order.
+const BASIC: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::IntenseBlack,
+    Color::IntenseRed,
+    Color::IntenseGreen,
+    Color::IntenseYellow,
+    Color::IntenseBlue,
+    Color::IntenseMagenta,
+    Color::IntenseCyan,
+    Color::IntenseWhite,
+];
+
+/// RGB values of the xterm default palette.
+fn xterm_rgb(code: u8) -> (u8, u8, u8) {
+    const SYSTEM: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    match code {
+        0..=15 => SYSTEM[code as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = code - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let grey = 8 + (code - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+/// Returns the code in `codes` whose xterm color is perceptually closest to `rgb`.
+fn nearest(codes: std::ops::RangeInclusive<u8>, rgb: (u8, u8, u8)) -> u8 {
+    let target = lab(rgb);
+    let distance = |code: &u8| {
+        let (l, a, b) = lab(xterm_rgb(*code));
+        (l - target.0).powi(2) + (a - target.1).powi(2) + (b - target.2).powi(2)
+    };
+    codes
+        .min_by(|x, y| distance(x).total_cmp(&distance(y)))
+        .unwrap()
+}
+
+/// Returns the perceptual distance between two colors (CIE76 delta E).
+pub(crate) fn delta_e(a: Color, b: Color) -> f64 {
+    let (a, b) = (lab(a.to_rgb()), lab(b.to_rgb()));
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// Converts an sRGB color to CIELAB (D65 white point).
+fn lab((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    let linear = |v: u8| {
+        let v = v as f64 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// Parses a color function such as `darken(red, 20%)`, or returns `None`
+/// if `lower` is not a call to one.
+fn parse_function(s: &str, value: &str) -> Option<Result<Color, String>> {
+    let (name, args) = [
+        "lighten",
+        "darken",
+        "saturate",
+        "desaturate",
+        "mix",
+        "invert",
+    ]
+    .iter()
+    .find_map(|name| Some((*name, function_args(value, name)?)))?;
+    let args = split_top_level(args);
+    let expected = match name {
+        "invert" => 1,
+        "mix" => 3,
+        _ => 2,
+    };
+    if args.len() != expected {
+        return Some(Err(format!(
+            "Expected {} argument{} in {}, found {}",
+            expected,
+            if expected == 1 { "" } else { "s" },
+            s,
+            args.iter().filter(|a| !a.is_empty()).count()
+        )));
+    }
+    let color = |arg: &str| Color::from_str(arg);
+    let amount = |arg: &str| {
+        arg.strip_suffix('%')
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .filter(|v| (0.0..=100.0).contains(v))
+            .map(|v| v / 100.0)
+            .ok_or_else(|| format!("Invalid amount: {}. Expected 0% to 100% in {}", arg, s))
+    };
+    Some((|| {
+        Ok(match name {
+            "lighten" => color(args[0])?.lighten(amount(args[1])?),
+            "darken" => color(args[0])?.darken(amount(args[1])?),
+            "saturate" => color(args[0])?.saturate(amount(args[1])?),
+            "desaturate" => color(args[0])?.desaturate(amount(args[1])?),
+            "mix" => color(args[0])?.mix(color(args[1])?, amount(args[2])?),
+            _ => color(args[0])?.invert(),
+        })
+    })())
+}
+
+/// Splits arguments on the commas outside of nested parentheses.
+pub(crate) fn split_top_level(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+/// Returns the text between the parentheses of `name(...)`.
+fn function_args<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = s.get(..name.len())?;
+    if !prefix.eq_ignore_ascii_case(name) {
+        return None;
+    }
+    s[name.len()..]
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+/// Splits the comma-separated arguments of a color function.
+fn split_args<'a>(s: &str, args: &'a str) -> Result<[&'a str; 3], String> {
+    let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+    <[&str; 3]>::try_from(parts).map_err(|parts| {
+        format!(
+            "Expected 3 arguments in {}, found {}",
+            s,
+            parts.iter().filter(|p| !p.is_empty()).count()
+        )
+    })
+}
+
+/// Parses the digits of `#rgb` or `#rrggbb`.
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    match hex.len() {
+        3 => Some(Color::Rgb(digit(0) * 17, digit(1) * 17, digit(2) * 17)),
+        6 => Some(Color::Rgb(byte(0), byte(2), byte(4))),
+        _ => None,
+    }
+}
+
+/// Converts a hue in degrees and saturation and lightness in `0..=1` to RGB.
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round() as u8;
+    Color::Rgb(channel(r), channel(g), channel(b))
+}
+
+/// Converts RGB to a hue in degrees and saturation and lightness in `0..=1`.
+fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation, lightness)
+}
+
+/// CSS color names, except the ones shared with the ANSI colors above.
+const CSS_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (128, 128, 128)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (128, 128, 128)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("maroon", (128, 0, 0)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (128, 0, 128)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("wheat", (245, 222, 179)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+impl fmt::Display for Color {
+    /// Formats the color in TCSS syntax, as accepted by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::Black => write!(f, "black"),
+            Color::Red => write!(f, "red"),
+            Color::Green => write!(f, "green"),
+            Color::Yellow => write!(f, "yellow"),
+            Color::Blue => write!(f, "blue"),
+            Color::Magenta => write!(f, "magenta"),
+            Color::Cyan => write!(f, "cyan"),
+            Color::White => write!(f, "white"),
+            Color::IntenseBlack => write!(f, "i-black"),
+            Color::IntenseRed => write!(f, "i-red"),
+            Color::IntenseGreen => write!(f, "i-green"),
+            Color::IntenseYellow => write!(f, "i-yellow"),
+            Color::IntenseBlue => write!(f, "i-blue"),
+            Color::IntenseMagenta => write!(f, "i-magenta"),
+            Color::IntenseCyan => write!(f, "i-cyan"),
+            Color::IntenseWhite => write!(f, "i-white"),
+            Color::Code(code) => write!(f, "{}", code),
+            Color::Rgb(r, g, b) => write!(f, "rgb({}, {}, {})", r, g, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Color;
+    use crate::capabilities::ColorDepth;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_css_colors() {
+        assert_eq!(Color::from_str("#ff8800"), Ok(Color::Rgb(255, 136, 0)));
+        assert_eq!(Color::from_str("#F80"), Ok(Color::Rgb(255, 136, 0)));
+        assert_eq!(
+            Color::from_str("hsl(30, 100%, 50%)"),
+            Ok(Color::Rgb(255, 128, 0))
+        );
+        assert_eq!(
+            Color::from_str("hsl(240deg, 100%, 25%)"),
+            Ok(Color::Rgb(0, 0, 128))
+        );
+        assert_eq!(
+            Color::from_str("RebeccaPurple"),
+            Ok(Color::Rgb(102, 51, 153))
+        );
+        assert_eq!(Color::from_str("red"), Ok(Color::Red));
+        assert_eq!(Color::from_str("196"), Ok(Color::Code(196)));
+
+        assert_eq!(
+            Color::from_str("#ff88"),
+            Err("Invalid hex color: #ff88. Expected #rgb or #rrggbb with hex digits".into())
+        );
+        assert_eq!(
+            Color::from_str("hsl(30, 100, 50%)"),
+            Err("Invalid saturation: 100. Expected 0% to 100% in hsl(30, 100, 50%)".into())
+        );
+        assert_eq!(
+            Color::from_str("rgb(255, 0)"),
+            Err("Expected 3 arguments in rgb(255, 0), found 2".into())
+        );
+        assert_eq!(
+            Color::from_str("rgb(256, 0, 0)"),
+            Err("Invalid RGB value: 256. Expected 0 to 255 in rgb(256, 0, 0)".into())
+        );
+        assert_eq!(
+            Color::from_str("300"),
+            Err("Invalid color code: 300. Expected 0 to 255".into())
+        );
+        assert_eq!(Color::from_str("  "), Err("Missing color value".into()));
+        // Errors quote the input as written
+        assert_eq!(
+            Color::from_str("RGB(255, 0, X)"),
+            Err("Invalid RGB value: X. Expected 0 to 255 in RGB(255, 0, X)".into())
+        );
+        assert_eq!(Color::from_str("Bleu"), Err("Unknown color: Bleu".into()));
+    }
+
+    #[test]
+    fn test_downsample() {
+        let orange = Color::Rgb(255, 135, 0);
+        assert_eq!(orange.downsample(ColorDepth::TrueColor), Some(orange));
+        assert_eq!(
+            orange.downsample(ColorDepth::Ansi256),
+            Some(Color::Code(208))
+        );
+        assert_eq!(
+            Color::Rgb(250, 250, 250).downsample(ColorDepth::Ansi256),
+            Some(Color::Code(231))
+        );
+        assert_eq!(
+            Color::Rgb(128, 128, 128).downsample(ColorDepth::Ansi256),
+            Some(Color::Code(244))
+        );
+        assert_eq!(
+            Color::Rgb(200, 10, 10).downsample(ColorDepth::Ansi16),
+            Some(Color::Red)
+        );
+        assert_eq!(
+            Color::Code(9).downsample(ColorDepth::Ansi16),
+            Some(Color::IntenseRed)
+        );
+        assert_eq!(
+            Color::Code(21).downsample(ColorDepth::Ansi16),
+            Some(Color::Blue)
+        );
+        assert_eq!(
+            Color::Blue.downsample(ColorDepth::Ansi16),
+            Some(Color::Blue)
+        );
+        assert_eq!(Color::Blue.downsample(ColorDepth::NoColor), None);
+    }
+
+    #[test]
+    fn test_color_helpers() {
+        assert_eq!(Color::Red.to_rgb(), (205, 0, 0));
+        assert_eq!(Color::Code(196).to_rgb(), (255, 0, 0));
+        assert_eq!(Color::Code(244).to_rgb(), (128, 128, 128));
+
+        let orange = Color::Rgb(255, 128, 0);
+        assert_eq!(orange.darken(0.2), Color::Rgb(153, 77, 0));
+        assert_eq!(orange.lighten(0.2), Color::Rgb(255, 179, 102));
+        assert_eq!(Color::Rgb(128, 64, 64).saturate(1.0), Color::Rgb(192, 0, 0));
+        assert_eq!(orange.desaturate(1.0), Color::Rgb(128, 128, 128));
+        assert_eq!(
+            Color::Black.mix(Color::Rgb(255, 255, 255), 0.5),
+            Color::Rgb(128, 128, 128)
+        );
+        assert_eq!(orange.invert(), Color::Rgb(0, 127, 255));
+
+        assert_eq!(
+            Color::from_str("darken(#ff8000, 20%)"),
+            Ok(Color::Rgb(153, 77, 0))
+        );
+        assert_eq!(
+            Color::from_str("mix(rgb(0, 0, 0), white, 0%)"),
+            Ok(Color::Rgb(0, 0, 0))
+        );
+        assert_eq!(
+            Color::from_str("invert(lighten(black, 0%))"),
+            Ok(Color::Rgb(255, 255, 255))
+        );
+        assert_eq!(
+            Color::from_str("darken(red)"),
+            Err("Expected 2 arguments in darken(red), found 1".into())
+        );
+        assert_eq!(
+            Color::from_str("darken(red, 0.2)"),
+            Err("Invalid amount: 0.2. Expected 0% to 100% in darken(red, 0.2)".into())
+        );
+    }
+}
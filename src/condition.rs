@@ -0,0 +1,173 @@
+use crate::capabilities::{Capabilities, ColorDepth};
+use std::fmt;
+use std::str::FromStr;
+
+/// A terminal feature that `@when` conditions can compare.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Feature {
+    ColorDepth,
+    Width,
+    Height,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Feature::ColorDepth => "color-depth",
+            Feature::Width => "width",
+            Feature::Height => "height",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Comparison {
+    fn holds(&self, left: u32, right: u32) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+}
+
+/// A single test such as `no-color` or `width < 80`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Clause {
+    NoColor,
+    Compare(Feature, Comparison, u32),
+}
+
+impl Clause {
+    fn matches(&self, caps: &Capabilities) -> bool {
+        match self {
+            Clause::NoColor => caps.color_depth == ColorDepth::NoColor,
+            Clause::Compare(feature, comparison, value) => {
+                let actual = match feature {
+                    Feature::ColorDepth => Some(caps.color_depth.colors()),
+                    Feature::Width => caps.width.map(u32::from),
+                    Feature::Height => caps.height.map(u32::from),
+                };
+                // Unknown sizes never match
+                actual.is_some_and(|actual| comparison.holds(actual, *value))
+            }
+        }
+    }
+}
+
+impl FromStr for Clause {
+    type Err = String;
+
+    /// Parses `no-color` or `<feature> <op> <value>`, e.g. `color-depth >= 256`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "no-color" {
+            return Ok(Clause::NoColor);
+        }
+
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let [feature, comparison, value] = parts[..] else {
+            return Err(format!(
+                "Invalid condition: {}. Use `no-color` or `<feature> <op> <value>`",
+                s
+            ));
+        };
+
+        let feature = match feature {
+            "color-depth" => Feature::ColorDepth,
+            "width" => Feature::Width,
+            "height" => Feature::Height,
+            _ => return Err(format!("Unknown feature: {}", feature)),
+        };
+        let comparison = match comparison {
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            "=" | "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            _ => return Err(format!("Unknown comparison: {}", comparison)),
+        };
+        let value = match (feature, value) {
+            (Feature::ColorDepth, "truecolor") => ColorDepth::TrueColor.colors(),
+            _ => value
+                .parse()
+                .map_err(|_| format!("Invalid condition value: {}", value))?,
+        };
+
+        Ok(Clause::Compare(feature, comparison, value))
+    }
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Clause::NoColor => write!(f, "no-color"),
+            Clause::Compare(feature, comparison, value) => {
+                write!(f, "{} {} {}", feature, comparison, value)
+            }
+        }
+    }
+}
+
+/// The condition of a `@when` block; every clause has to hold.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Condition {
+    clauses: Vec<Clause>,
+}
+
+impl Condition {
+    pub(crate) fn new(clauses: Vec<Clause>) -> Self {
+        Condition { clauses }
+    }
+
+    /// Returns a condition that holds when both `self` and `other` hold.
+    pub(crate) fn and(&self, other: &Condition) -> Condition {
+        let mut clauses = self.clauses.clone();
+        clauses.extend(other.clauses.iter().cloned());
+        Condition { clauses }
+    }
+
+    pub(crate) fn matches(&self, caps: &Capabilities) -> bool {
+        self.clauses.iter().all(|clause| clause.matches(caps))
+    }
+}
+
+impl fmt::Display for Condition {
+    /// Formats the condition as written after `@when`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if i > 0 {
+                write!(f, " and ")?;
+            }
+            write!(f, "{}", clause)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! WCAG 2 contrast checks between foreground and background colors.
+
+use crate::color::Color;
+use crate::style::Style;
+use std::error::Error;
+use std::fmt;
+
+/// A WCAG conformance level for text contrast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContrastLevel {
+    AaLarge, // AA for large or bold text, 3:1
+    Aa,      // AA for normal text, 4.5:1
+    Aaa,     // AAA for normal text, 7:1
+}
+
+impl ContrastLevel {
+    /// Returns the minimum contrast ratio the level requires.
+    pub fn min_ratio(&self) -> f64 {
+        match self {
+            ContrastLevel::AaLarge => 3.0,
+            ContrastLevel::Aa => 4.5,
+            ContrastLevel::Aaa => 7.0,
+        }
+    }
+}
+
+impl fmt::Display for ContrastLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ContrastLevel::AaLarge => "AA (large text)",
+            ContrastLevel::Aa => "AA",
+            ContrastLevel::Aaa => "AAA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A foreground and background pair below the required contrast.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContrastIssue {
+    /// Element the colors belong to, when found by a `Termio` lint
+    pub element: Option<String>,
+    pub fg: Color,
+    pub bg: Color,
+    /// Actual contrast ratio, from 1.0 to 21.0
+    pub ratio: f64,
+    /// Level that was not met
+    pub level: ContrastLevel,
+}
+
+impl fmt::Display for ContrastIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(element) = &self.element {
+            write!(f, "@element \"{}\": ", element)?;
+        }
+        write!(
+            f,
+            "contrast of {} on {} is {:.2}:1, WCAG {} requires {}:1",
+            self.fg,
+            self.bg,
+            self.ratio,
+            self.level,
+            self.level.min_ratio()
+        )
+    }
+}
+
+impl Error for ContrastIssue {}
+
+impl Color {
+    /// Returns the WCAG relative luminance, from 0.0 (black) to 1.0 (white).
+    pub fn luminance(&self) -> f64 {
+        let (r, g, b) = self.to_rgb();
+        let linear = |v: u8| {
+            let v = v as f64 / 255.0;
+            if v <= 0.03928 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// Returns the WCAG contrast ratio between two colors, from 1.0 to 21.0.
+    pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+        let (a, b) = (a.luminance(), b.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Returns this color, or the closest lighter or darker shade of it that
+    /// meets `level` on `bg`.
+    ///
+    /// Falls back to black or white when no shade is readable enough.
+    pub fn readable_on(&self, bg: Color, level: ContrastLevel) -> Color {
+        let min = level.min_ratio();
+        if Color::contrast_ratio(*self, bg) >= min {
+            return *self;
+        }
+        // Move away from the background's lightness
+        let darker = bg.luminance() > 0.18;
+        (1..=100)
+            .map(|step| {
+                let amount = step as f64 / 100.0;
+                if darker {
+                    self.darken(amount)
+                } else {
+                    self.lighten(amount)
+                }
+            })
+            .find(|shade| Color::contrast_ratio(*shade, bg) >= min)
+            .unwrap_or_else(|| {
+                let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+                if Color::contrast_ratio(black, bg) >= Color::contrast_ratio(white, bg) {
+                    black
+                } else {
+                    white
+                }
+            })
+    }
+}
+
+impl Style {
+    /// Checks that the foreground is readable on the background.
+    ///
+    /// Styles without both colors pass, since the terminal's own colors
+    /// are unknown.
+    pub fn check_contrast(&self, level: ContrastLevel) -> Result<(), ContrastIssue> {
+        let (Some(fg), Some(bg)) = (self.fg, self.bg) else {
+            return Ok(());
+        };
+        let ratio = Color::contrast_ratio(fg, bg);
+        if ratio >= level.min_ratio() {
+            return Ok(());
+        }
+        Err(ContrastIssue {
+            element: None,
+            fg,
+            bg,
+            ratio,
+            level,
+        })
+    }
+
+    /// Returns the style with its foreground adjusted to meet `level`.
+    pub fn with_readable_fg(mut self, level: ContrastLevel) -> Self {
+        if let (Some(fg), Some(bg)) = (self.fg, self.bg) {
+            self.fg = Some(fg.readable_on(bg, level));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContrastLevel;
+    use crate::color::Color;
+    use crate::style::Style;
+
+    #[test]
+    fn test_contrast() {
+        let white = Color::Rgb(255, 255, 255);
+        let black = Color::Rgb(0, 0, 0);
+        assert!((Color::contrast_ratio(white, black) - 21.0).abs() < 1e-9);
+        assert!((Color::contrast_ratio(black, black) - 1.0).abs() < 1e-9);
+        let grey = Color::Rgb(118, 118, 118);
+        assert!((Color::contrast_ratio(grey, white) - 4.54).abs() < 0.01);
+
+        let style = Style::new().fg(Color::Rgb(170, 170, 170)).bg(white);
+        let issue = style.check_contrast(ContrastLevel::Aa).unwrap_err();
+        assert_eq!(
+            issue.to_string(),
+            "contrast of rgb(170, 170, 170) on rgb(255, 255, 255) is 2.32:1, WCAG AA requires 4.5:1"
+        );
+        assert!(style.check_contrast(ContrastLevel::AaLarge).is_err());
+        assert!(Style::new()
+            .fg(grey)
+            .check_contrast(ContrastLevel::Aaa)
+            .is_ok());
+
+        let fixed = style.with_readable_fg(ContrastLevel::Aa);
+        assert!(fixed.check_contrast(ContrastLevel::Aa).is_ok());
+        assert_eq!(fixed.fg, Some(Color::Rgb(116, 116, 116)));
+    }
+}
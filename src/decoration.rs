@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -18,12 +19,20 @@ pub enum Decoration {
     Strikethrough,
     Framed,
     Encircled,
+    CurlyUnderline,  // Needs extended underline support
+    DottedUnderline, // Needs extended underline support
+    DashedUnderline, // Needs extended underline support
 }
 
 impl Decoration {
     /// Converts the decoration to an ANSI escape sequence
     pub fn to_ansi(&self) -> String {
-        let code = match self {
+        format!("\x1b[{}m", self.sgr_code())
+    }
+
+    /// Returns the SGR parameter turning the decoration on
+    pub(crate) fn sgr_code(&self) -> &'static str {
+        match self {
             Decoration::None => "0",
             Decoration::Bold => "1",
             Decoration::Faint => "2",
@@ -40,8 +49,49 @@ impl Decoration {
             Decoration::Strikethrough => "9",
             Decoration::Framed => "51",
             Decoration::Encircled => "52",
-        };
-        format!("\x1b[{}m", code)
+            Decoration::CurlyUnderline => "4:3",
+            Decoration::DottedUnderline => "4:4",
+            Decoration::DashedUnderline => "4:5",
+        }
+    }
+
+    /// Returns whether this is one of the underline styles.
+    pub fn is_underline(&self) -> bool {
+        matches!(
+            self,
+            Decoration::Underline
+                | Decoration::DoubleUnderline
+                | Decoration::CurlyUnderline
+                | Decoration::DottedUnderline
+                | Decoration::DashedUnderline
+        )
+    }
+
+    /// Returns the underline decoration for a `text-decoration-style` value.
+    pub(crate) fn underline_style(style: &str) -> Result<Self, String> {
+        match style {
+            "solid" => Ok(Decoration::Underline),
+            "double" => Ok(Decoration::DoubleUnderline),
+            "curly" | "wavy" => Ok(Decoration::CurlyUnderline),
+            "dotted" => Ok(Decoration::DottedUnderline),
+            "dashed" => Ok(Decoration::DashedUnderline),
+            _ => Err(format!(
+                "Unknown underline style: {}. Use solid, double, curly, dotted or dashed",
+                style
+            )),
+        }
+    }
+
+    /// Returns the `text-decoration-style` value of an underline decoration.
+    pub(crate) fn underline_style_name(&self) -> Option<&'static str> {
+        match self {
+            Decoration::Underline => Some("solid"),
+            Decoration::DoubleUnderline => Some("double"),
+            Decoration::CurlyUnderline => Some("curly"),
+            Decoration::DottedUnderline => Some("dotted"),
+            Decoration::DashedUnderline => Some("dashed"),
+            _ => None,
+        }
     }
 
     /// Resets all decorations
@@ -71,7 +121,62 @@ impl FromStr for Decoration {
             "strikethrough" => Ok(Decoration::Strikethrough),
             "framed" => Ok(Decoration::Framed),
             "encircled" => Ok(Decoration::Encircled),
+            "curly-underline" => Ok(Decoration::CurlyUnderline),
+            "dotted-underline" => Ok(Decoration::DottedUnderline),
+            "dashed-underline" => Ok(Decoration::DashedUnderline),
             _ => Err(format!("Unknown decoration: {}", s)),
         }
     }
 }
+
+impl fmt::Display for Decoration {
+    /// Formats the decoration in TCSS syntax, as accepted by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Decoration::None => "none",
+            Decoration::Bold => "bold",
+            Decoration::Faint => "faint",
+            Decoration::Italic => "italic",
+            Decoration::Underline => "underline",
+            Decoration::Blink => "blink",
+            Decoration::RapidBlink => "rapid-blink",
+            Decoration::Reverse => "reverse",
+            Decoration::Conceal => "conceal",
+            Decoration::CrossedOut => "crossed-out",
+            Decoration::DoubleUnderline => "double-underline",
+            Decoration::Overline => "overline",
+            Decoration::Hidden => "hidden",
+            Decoration::Strikethrough => "strikethrough",
+            Decoration::Framed => "framed",
+            Decoration::Encircled => "encircled",
+            Decoration::CurlyUnderline => "curly-underline",
+            Decoration::DottedUnderline => "dotted-underline",
+            Decoration::DashedUnderline => "dashed-underline",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoration;
+    use crate::capabilities::{Capabilities, ColorPolicy};
+    use crate::color::Color;
+    use crate::styled_text::StyledText;
+
+    #[test]
+    fn test_extended_underlines() {
+        let text = "typo"
+            .decoration(Decoration::CurlyUnderline)
+            .underline_color(Color::Red)
+            .color_policy(ColorPolicy::Always);
+
+        let extended = format!("{}", text.clone());
+        assert!(extended.contains("\x1b[4:3;58;5;1mtypo"));
+        let plain = format!(
+            "{}",
+            text.capabilities(Capabilities::default().undercurl(false))
+        );
+        assert!(plain.contains("\x1b[4mtypo"));
+    }
+}
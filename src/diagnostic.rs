@@ -0,0 +1,143 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+
+/// A located TCSS problem, rendered in a rustc-like format:
+///
+/// ```text
+/// error: Unknown color: blu
+///  --> styles.tcss:4:12
+///   |
+/// 4 |     color: blu;
+///   |            ^^^
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Human readable description of the problem
+    pub message: String,
+    /// File the source was read from, if any
+    pub path: Option<PathBuf>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number (in characters)
+    pub column: usize,
+    /// Byte range of the offending text within the whole source
+    pub span: Range<usize>,
+    /// The full source line the span points into
+    pub source_line: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic for `span`, a byte range local to `source_line`.
+    pub(crate) fn new(
+        message: impl Into<String>,
+        source_line: &str,
+        line: usize,
+        line_offset: usize,
+        span: Range<usize>,
+    ) -> Self {
+        let column = source_line[..span.start].chars().count() + 1;
+        Diagnostic {
+            message: message.into(),
+            path: None,
+            line,
+            column,
+            span: line_offset + span.start..line_offset + span.end,
+            source_line: source_line.to_string(),
+        }
+    }
+
+    /// Sets the file path shown in the location line.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Renders the source line with a caret marker under the offending span.
+    pub fn snippet(&self) -> String {
+        let gutter = " ".repeat(self.line.to_string().len());
+        let len = self.source_line.len();
+        let start = self
+            .source_line
+            .char_indices()
+            .nth(self.column - 1)
+            .map_or(len, |(i, _)| i);
+        let end = (start + self.span.len()).min(len);
+        let prefix = &self.source_line[..start];
+        let marked = &self.source_line[start..end];
+        let indent = prefix.replace('\t', "    ").width();
+        let carets = "^".repeat(marked.width().max(1));
+
+        format!(
+            "{gutter} |\n{} | {}\n{gutter} | {}{}",
+            self.line,
+            self.source_line.replace('\t', "    "),
+            " ".repeat(indent),
+            carets
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "<tcss>".to_string(),
+        };
+        let gutter = " ".repeat(self.line.to_string().len());
+        writeln!(f, "error: {}", self.message)?;
+        writeln!(f, "{gutter}--> {}:{}:{}", path, self.line, self.column)?;
+        write!(f, "{}", self.snippet())
+    }
+}
+
+/// Custom error type for TCSS parsing errors
+#[derive(Debug)]
+pub enum ParseError {
+    InvalidSyntax(Box<Diagnostic>),
+    DuplicateElement {
+        name: String,
+        diagnostic: Box<Diagnostic>,
+    },
+    Io(String),
+}
+
+impl ParseError {
+    /// Returns the located diagnostic, if the error points into a source.
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            ParseError::InvalidSyntax(diagnostic) => Some(diagnostic),
+            ParseError::DuplicateElement { diagnostic, .. } => Some(diagnostic),
+            ParseError::Io(_) => None,
+        }
+    }
+
+    /// Sets the file path of the contained diagnostic, unless it already
+    /// has one (errors from imported sheets keep their own path).
+    pub(crate) fn with_path(mut self, path: &Path) -> Self {
+        match &mut self {
+            ParseError::InvalidSyntax(diagnostic)
+            | ParseError::DuplicateElement { diagnostic, .. } => {
+                if diagnostic.path.is_none() {
+                    diagnostic.path = Some(path.to_path_buf());
+                }
+            }
+            ParseError::Io(_) => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidSyntax(diagnostic) => write!(f, "{}", diagnostic),
+            ParseError::DuplicateElement { diagnostic, .. } => write!(f, "{}", diagnostic),
+            ParseError::Io(msg) => write!(f, "I/O error: {}", msg),
+        }
+    }
+}
+
+impl Error for ParseError {}
@@ -0,0 +1,166 @@
+use crate::color::{split_top_level, Color};
+use std::fmt;
+use std::str::FromStr;
+
+/// The axis a gradient runs along.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientDirection {
+    Horizontal, // Across each line, left to right
+    Vertical,   // Across lines, top to bottom
+}
+
+/// A linear gradient through evenly spaced color stops.
+///
+/// Rendered per character (horizontal) or per line (vertical) with
+/// interpolated RGB colors, which are downsampled like any other color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub direction: GradientDirection,
+    pub stops: Vec<Color>,
+}
+
+impl Gradient {
+    /// Creates a horizontal gradient.
+    pub fn horizontal(stops: Vec<Color>) -> Self {
+        Gradient {
+            direction: GradientDirection::Horizontal,
+            stops,
+        }
+    }
+
+    /// Creates a vertical gradient.
+    pub fn vertical(stops: Vec<Color>) -> Self {
+        Gradient {
+            direction: GradientDirection::Vertical,
+            stops,
+        }
+    }
+
+    /// Returns the color at `t`, from 0.0 (first stop) to 1.0 (last stop).
+    pub fn at(&self, t: f64) -> Option<Color> {
+        let last = self.stops.len().checked_sub(1)?;
+        if last == 0 {
+            return Some(self.stops[0]);
+        }
+        let position = t.clamp(0.0, 1.0) * last as f64;
+        let i = (position.floor() as usize).min(last - 1);
+        Some(self.stops[i].mix(self.stops[i + 1], position - i as f64))
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = String;
+
+    /// Parses `linear-gradient([to right | to bottom,] <color>, <color>...)`.
+    ///
+    /// `to left` and `to top` reverse the stops. Without a direction the
+    /// gradient runs to the right.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let args = s
+            .strip_prefix("linear-gradient")
+            .and_then(|rest| rest.trim_start().strip_prefix('('))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| format!("Invalid gradient: {}. Use `linear-gradient(...)`", s))?;
+        let mut args = split_top_level(args);
+
+        let mut gradient = Gradient::horizontal(Vec::new());
+        let mut reverse = false;
+        if let Some(direction) = args.first().and_then(|a| a.strip_prefix("to ")) {
+            match direction.trim() {
+                "right" => {}
+                "left" => reverse = true,
+                "bottom" => gradient.direction = GradientDirection::Vertical,
+                "top" => {
+                    gradient.direction = GradientDirection::Vertical;
+                    reverse = true;
+                }
+                other => {
+                    return Err(format!(
+                        "Unknown gradient direction: to {}. Use `to right`, `to left`, `to bottom` or `to top`",
+                        other
+                    ))
+                }
+            }
+            args.remove(0);
+        }
+
+        if args.len() < 2 || args.iter().any(|a| a.is_empty()) {
+            return Err(format!("Expected at least 2 colors in {}", s));
+        }
+        gradient.stops = args
+            .into_iter()
+            .map(Color::from_str)
+            .collect::<Result<_, _>>()?;
+        if reverse {
+            gradient.stops.reverse();
+        }
+        Ok(gradient)
+    }
+}
+
+impl fmt::Display for Gradient {
+    /// Formats the gradient in TCSS syntax, as accepted by `from_str`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "linear-gradient(")?;
+        if self.direction == GradientDirection::Vertical {
+            write!(f, "to bottom, ")?;
+        }
+        let stops: Vec<String> = self.stops.iter().map(|c| c.to_string()).collect();
+        write!(f, "{})", stops.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gradient, GradientDirection};
+    use crate::capabilities::{ColorDepth, ColorPolicy};
+    use crate::color::Color;
+    use crate::styled_text::StyledText;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_gradient() {
+        let gradient = Gradient::from_str("linear-gradient(#ff0000, #0000ff)").unwrap();
+        assert_eq!(gradient.direction, GradientDirection::Horizontal);
+        assert_eq!(gradient.at(0.0), Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(gradient.at(0.5), Some(Color::Rgb(128, 0, 128)));
+        assert_eq!(gradient.at(1.0), Some(Color::Rgb(0, 0, 255)));
+
+        let vertical =
+            Gradient::from_str("linear-gradient(to top, red, rgb(0, 0, 0), blue)").unwrap();
+        assert_eq!(vertical.direction, GradientDirection::Vertical);
+        assert_eq!(
+            vertical.stops,
+            [Color::Blue, Color::Rgb(0, 0, 0), Color::Red]
+        );
+        assert_eq!(vertical.at(0.5), Some(Color::Rgb(0, 0, 0)));
+        assert_eq!(
+            vertical.to_string(),
+            "linear-gradient(to bottom, blue, rgb(0, 0, 0), red)"
+        );
+
+        assert_eq!(
+            Gradient::from_str("linear-gradient(red)"),
+            Err("Expected at least 2 colors in linear-gradient(red)".into())
+        );
+        assert_eq!(
+            Gradient::from_str("linear-gradient(red, blu)"),
+            Err("Unknown color: blu".into())
+        );
+    }
+
+    #[test]
+    fn test_gradient_rendering() {
+        let gradient = Gradient::horizontal(vec![Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255)]);
+        let text = "abc"
+            .color(Color::Green)
+            .fg_gradient(gradient)
+            .color_policy(ColorPolicy::Always);
+
+        let truecolor = format!("{}", text.clone());
+        assert!(truecolor.contains("\x1b[38;2;255;0;0ma\x1b[38;2;128;0;128mb\x1b[38;2;0;0;255mc"));
+        let ansi256 = format!("{}", text.color_depth(ColorDepth::Ansi256));
+        assert!(ansi256.contains("\x1b[38;5;196ma\x1b[38;5;90mb\x1b[38;5;21mc"));
+    }
+}
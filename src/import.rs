@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Loads the sheets referenced by `@import "name";`.
+///
+/// `from` is the path of the importing sheet, or `None` for sheets parsed
+/// from a string. The returned path identifies the imported sheet: it is
+/// used to resolve its own imports, to detect import cycles and to label
+/// its diagnostics.
+pub trait ImportResolver: fmt::Debug + Send + Sync {
+    fn resolve(&self, name: &str, from: Option<&Path>) -> Result<(PathBuf, String), String>;
+}
+
+/// Reads imports from disk, relative to the importing file.
+///
+/// This is the resolver used when none is set on the `Termio`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileResolver;
+
+impl ImportResolver for FileResolver {
+    fn resolve(&self, name: &str, from: Option<&Path>) -> Result<(PathBuf, String), String> {
+        let path = relative_to(name, from);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot import {}: {}", path.display(), e))?;
+        Ok((path, content))
+    }
+}
+
+/// Serves imports from sheets registered in memory, e.g. with `include_str!`.
+///
+/// Names are resolved relative to the importing sheet just like files, so
+/// `@import "base.tcss";` inside `themes/dark.tcss` looks up `themes/base.tcss`.
+#[derive(Clone, Debug, Default)]
+pub struct EmbeddedResolver {
+    sheets: HashMap<PathBuf, String>,
+}
+
+impl EmbeddedResolver {
+    /// Creates a resolver without any sheets.
+    pub fn new() -> Self {
+        EmbeddedResolver::default()
+    }
+
+    /// Registers a sheet under the given path.
+    pub fn with(mut self, path: impl AsRef<Path>, content: impl Into<String>) -> Self {
+        self.sheets.insert(normalize(path.as_ref()), content.into());
+        self
+    }
+}
+
+impl ImportResolver for EmbeddedResolver {
+    fn resolve(&self, name: &str, from: Option<&Path>) -> Result<(PathBuf, String), String> {
+        let path = relative_to(name, from);
+        match self.sheets.get(&path) {
+            Some(content) => Ok((path, content.clone())),
+            None => Err(format!("Unknown embedded sheet: {}", path.display())),
+        }
+    }
+}
+
+/// Joins `name` onto the directory of `from`.
+fn relative_to(name: &str, from: Option<&Path>) -> PathBuf {
+    match from.and_then(Path::parent) {
+        Some(dir) => normalize(&dir.join(name)),
+        None => normalize(Path::new(name)),
+    }
+}
+
+/// Lexically removes `.` and `..` components so equal paths compare equal.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
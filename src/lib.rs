@@ -1,16 +1,37 @@
 mod border;
+mod capabilities;
 mod color;
+mod condition;
+mod contrast;
 mod decoration;
+mod diagnostic;
+mod gradient;
+mod import;
 #[macro_use]
 mod macros;
+mod markup;
+mod palette;
+mod parser;
+mod reload;
+mod sgr;
 mod termio;
+mod vision;
 mod style;
 mod styled_text;
 pub mod prelude;
 
-pub use border::BorderStyle;
+pub use border::{BorderChars, BorderStyle};
+pub use capabilities::{Capabilities, ColorDepth, ColorPolicy, Stream};
 pub use color::Color;
+pub use contrast::{ContrastIssue, ContrastLevel};
 pub use decoration::Decoration;
-pub use termio::Termio;
+pub use diagnostic::{Diagnostic, ParseError};
+pub use gradient::{Gradient, GradientDirection};
+pub use import::{EmbeddedResolver, FileResolver, ImportResolver};
+pub use markup::{escape_markup, MarkupArg, MarkupError};
+pub use palette::Palette;
+pub use termio::{MergeError, MergePolicy, Termio};
+pub use reload::WatchedTermio;
 pub use style::Style;
 pub use styled_text::{StyledString, StyledText};
+pub use vision::{ColorBlindness, ColorConflict};
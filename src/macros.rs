@@ -35,10 +35,62 @@ macro_rules! tcss {
     }};
 }
 
+/// Formats inline markup into a `String`, styling each tag with the
+/// element of the same name from a `Termio` sheet
+///
+/// Only the template is markup: positional and named (`name = value`)
+/// arguments are escaped, so they render literally. Variables cannot be
+/// captured inline, as their values would be read as markup:
+///
+/// ```compile_fail
+/// # use termio::{tformat, Termio};
+/// let file = "<path>";
+/// tformat!(Termio::new(), "Wrote {file}");
+/// ```
+///
+/// Unknown tags render unstyled; use `Termio::markup` to catch them.
+#[macro_export]
+macro_rules! tformat {
+    // Wraps each argument, named or positional, in `MarkupArg`
+    (@args $tcss:expr, $fmt:literal, [$($done:tt)*]) => {
+        // A template from `concat!` cannot capture variables inline
+        $tcss
+            .markup_recovering(&format!(concat!($fmt), $($done)*))
+            .0
+            .to_string()
+    };
+    (@args $tcss:expr, $fmt:literal, [$($done:tt)*] $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::tformat!(@args $tcss, $fmt, [$($done)* $name = $crate::MarkupArg(&$value),] $($($rest)*)?)
+    };
+    (@args $tcss:expr, $fmt:literal, [$($done:tt)*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::tformat!(@args $tcss, $fmt, [$($done)* $crate::MarkupArg(&$value),] $($($rest)*)?)
+    };
+    ($tcss:expr, $fmt:literal $(, $($args:tt)*)?) => {
+        $crate::tformat!(@args $tcss, $fmt, [] $($($args)*)?)
+    };
+}
+
+/// Prints inline markup styled with a `Termio` sheet
+#[macro_export]
+macro_rules! tprint {
+    ($tcss:expr, $($arg:tt)*) => {
+        print!("{}", $crate::tformat!($tcss, $($arg)*))
+    };
+}
+
+/// Prints inline markup styled with a `Termio` sheet, with a newline
+#[macro_export]
+macro_rules! tprintln {
+    ($tcss:expr, $($arg:tt)*) => {
+        println!("{}", $crate::tformat!($tcss, $($arg)*))
+    };
+}
+
 // Helper macro for testing
 #[cfg(test)]
 mod tests {
     use crate::border::BorderStyle;
+    use crate::capabilities::ColorPolicy;
     use crate::color::Color;
     use crate::decoration::Decoration;
     use crate::StyledText;
@@ -85,10 +137,47 @@ mod tests {
             }
         };
 
+        let parser = parser.with_color_policy(ColorPolicy::Always);
         let header = "Test Header".style("header", &parser);
         let warning = "Test Warning".style("warning", &parser);
 
-        assert!(format!("{}", header).contains("\x1b[32m")); // green
-        assert!(format!("{}", warning).contains("\x1b[33m")); // yellow
+        assert!(format!("{}", header).contains("\x1b[1;32mTest Header\x1b[22;39m")); // bold green
+        assert!(format!("{}", warning).contains("\x1b[1;3;33mTest Warning")); // bold italic yellow
+    }
+
+    #[test]
+    fn test_tformat_macro() {
+        let mut tcss = crate::Termio::new();
+        tcss.parse("@element \"ok\" {\n    color: green;\n}")
+            .unwrap();
+        let tcss = tcss.with_color_policy(ColorPolicy::Always);
+
+        let seconds = 3.2;
+        let text = tformat!(tcss, "Build <ok>passed</ok> in {}s", seconds);
+        assert_eq!(text, "Build \x1b[32mpassed\x1b[39m in 3.2s");
+
+        // Arguments render literally and keep their format options
+        let text = tformat!(tcss, "<ok>{}</ok> {:.1}s", "</ok><ok>\\", 2.25);
+        assert_eq!(text, "\x1b[32m</ok><ok>\\\x1b[39m 2.2s");
+        let text = tformat!(tcss, "[{:>5}] [{:.2}] {:?}", "<a>", "<ab>", "<");
+        assert_eq!(text, "[  <a>] [<a] \"<\"");
+
+        // Named arguments are escaped too, and may follow positional ones
+        let name = "<b>";
+        let text = tformat!(
+            tcss,
+            "{0} <ok>{name}</ok> {count:>3}",
+            1,
+            name = name,
+            count = 2,
+        );
+        assert_eq!(text, "1 \x1b[32m<b>\x1b[39m   2");
+    }
+
+    #[test]
+    fn test_tformat_macro_errors() {
+        // Tags the sheet does not define render unstyled
+        let tcss = crate::Termio::new().with_color_policy(ColorPolicy::Always);
+        assert_eq!(tformat!(tcss, "<missing>{}</missing>", 1), "1");
     }
 }
@@ -0,0 +1,315 @@
+//! Inline markup such as `Build <success>passed</success>`, where each tag
+//! is styled with the `@element` of the same name.
+//!
+//! Tags nest, and `\<` and `\\` write a literal `<` and `\`. A `<` that does
+//! not start a tag, as in `a < b`, is written as is.
+
+use crate::style::Style;
+use crate::styled_text::StyledString;
+use crate::termio::Termio;
+use std::error::Error;
+use std::fmt;
+
+/// Error in inline markup, with the byte offset of the offending tag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkupError {
+    /// A tag naming an element the sheet does not define
+    UnknownTag { name: String, offset: usize },
+    /// A closing tag for an element that is open, but not innermost
+    MismatchedTag {
+        expected: String,
+        found: String,
+        offset: usize,
+    },
+    /// A closing tag without an opening one
+    UnexpectedClosingTag { name: String, offset: usize },
+    /// An opening tag that is never closed
+    UnclosedTag { name: String, offset: usize },
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupError::UnknownTag { name, offset } => {
+                write!(f, "Unknown tag <{}> at offset {}", name, offset)
+            }
+            MarkupError::MismatchedTag {
+                expected,
+                found,
+                offset,
+            } => write!(
+                f,
+                "Expected </{}> but found </{}> at offset {}",
+                expected, found, offset
+            ),
+            MarkupError::UnexpectedClosingTag { name, offset } => {
+                write!(
+                    f,
+                    "Closing tag </{}> at offset {} was never opened",
+                    name, offset
+                )
+            }
+            MarkupError::UnclosedTag { name, offset } => {
+                write!(f, "Tag <{}> at offset {} is never closed", name, offset)
+            }
+        }
+    }
+}
+
+impl Error for MarkupError {}
+
+/// Escapes `text` so markup renders it literally.
+///
+/// Use it for values interpolated into markup, such as file names or
+/// user input.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('<', "\\<")
+}
+
+/// An argument of `tformat!`, formatted like the value and then escaped.
+#[doc(hidden)]
+pub struct MarkupArg<'a, T: ?Sized>(pub &'a T);
+
+impl<T: fmt::Display + ?Sized> fmt::Display for MarkupArg<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.0.to_string();
+        // Numbers and most text need no escaping and keep every format option
+        if !text.contains(['<', '\\']) {
+            return fmt::Display::fmt(self.0, f);
+        }
+        pad_escaped(f, &text)
+    }
+}
+
+impl<T: fmt::Debug + ?Sized> fmt::Debug for MarkupArg<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = format!("{:?}", self.0);
+        if !text.contains(['<', '\\']) {
+            return fmt::Debug::fmt(self.0, f);
+        }
+        pad_escaped(f, &text)
+    }
+}
+
+/// Applies precision, width and alignment to `text` like `Formatter::pad`,
+/// measuring the text before it is escaped
+fn pad_escaped(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    let text: String = match f.precision() {
+        Some(precision) => text.chars().take(precision).collect(),
+        None => text.to_string(),
+    };
+    let fill = f.width().unwrap_or(0).saturating_sub(text.chars().count());
+    let (before, after) = match f.align() {
+        Some(fmt::Alignment::Right) => (fill, 0),
+        Some(fmt::Alignment::Center) => (fill / 2, fill - fill / 2),
+        _ => (0, fill),
+    };
+    let pad = |count: usize| f.fill().to_string().repeat(count);
+    let padded = format!("{}{}{}", pad(before), text, pad(after));
+    f.write_str(&escape_markup(&padded))
+}
+
+impl Termio {
+    /// Renders inline markup with the elements of this sheet.
+    ///
+    /// Tags are styled like nested spans, so padding, margins and borders of
+    /// their elements are ignored. Fails on the first tag naming an unknown
+    /// element or closing the wrong element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use termio::Termio;
+    ///
+    /// let mut tcss = Termio::new();
+    /// tcss.parse(r#"
+    /// @element "success" {
+    ///     color: green;
+    /// }
+    /// "#).unwrap();
+    /// let text = tcss.markup("Build <success>passed</success>").unwrap();
+    /// assert_eq!(text.plain_text(), "Build passed");
+    /// assert!(tcss.markup("<fail>oops</fail>").is_err());
+    /// ```
+    pub fn markup(&self, text: &str) -> Result<StyledString, MarkupError> {
+        let (styled, errors) = self.markup_recovering(text);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(styled),
+        }
+    }
+
+    /// Renders inline markup, recovering from errors.
+    ///
+    /// Unknown tags render their text unstyled, stray closing tags are
+    /// dropped and open tags are closed at the end. All errors are returned
+    /// alongside the result.
+    pub fn markup_recovering(&self, text: &str) -> (StyledString, Vec<MarkupError>) {
+        let root = StyledString::new(String::new(), Style::new())
+            .capabilities(self.capabilities().clone())
+            .color_policy(self.color_policy());
+        let mut stack = vec![(String::new(), 0, root)];
+        let mut errors = Vec::new();
+        let mut literal = String::new();
+
+        let mut chars = text.char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\\' if text[offset + 1..].starts_with(['<', '\\']) => {
+                    literal.push(chars.next().unwrap().1);
+                }
+                '<' => match tag_at(&text[offset..]) {
+                    Some((name, closing, len)) => {
+                        // Tags are ASCII, one char per byte
+                        chars.nth(len - 2);
+                        stack.last_mut().unwrap().2.append(&literal);
+                        literal.clear();
+                        if closing {
+                            close_tag(&mut stack, name, offset, &mut errors);
+                        } else {
+                            let style = self.get_style(name).unwrap_or_else(|| {
+                                errors.push(MarkupError::UnknownTag {
+                                    name: name.to_string(),
+                                    offset,
+                                });
+                                Style::new()
+                            });
+                            let span = StyledString::new(String::new(), style);
+                            stack.push((name.to_string(), offset, span));
+                        }
+                    }
+                    None => literal.push(c),
+                },
+                c => literal.push(c),
+            }
+        }
+        stack.last_mut().unwrap().2.append(&literal);
+
+        while stack.len() > 1 {
+            let (name, offset, span) = stack.pop().unwrap();
+            errors.push(MarkupError::UnclosedTag { name, offset });
+            stack.last_mut().unwrap().2.push_span(span);
+        }
+        (stack.pop().unwrap().2, errors)
+    }
+}
+
+/// Closes the innermost open tag named `name`, and any opened after it.
+fn close_tag(
+    stack: &mut Vec<(String, usize, StyledString)>,
+    name: &str,
+    offset: usize,
+    errors: &mut Vec<MarkupError>,
+) {
+    let Some(depth) = stack.iter().skip(1).rposition(|(open, _, _)| open == name) else {
+        errors.push(MarkupError::UnexpectedClosingTag {
+            name: name.to_string(),
+            offset,
+        });
+        return;
+    };
+    let innermost = &stack.last().unwrap().0;
+    if innermost != name {
+        errors.push(MarkupError::MismatchedTag {
+            expected: innermost.clone(),
+            found: name.to_string(),
+            offset,
+        });
+    }
+    while stack.len() > depth + 1 {
+        let (_, _, span) = stack.pop().unwrap();
+        stack.last_mut().unwrap().2.push_span(span);
+    }
+}
+
+/// Returns the name of the tag `text` starts with, whether it is a
+/// closing tag and its length in bytes.
+fn tag_at(text: &str) -> Option<(&str, bool, usize)> {
+    let rest = text.strip_prefix('<')?;
+    let (closing, rest) = match rest.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let end = rest.find('>')?;
+    let name = &rest[..end];
+    let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if name.is_empty() || !name.chars().all(valid) {
+        return None;
+    }
+    Some((name, closing, 1 + closing as usize + end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_markup, MarkupError};
+    use crate::capabilities::ColorPolicy;
+    use crate::termio::Termio;
+
+    fn sheet() -> Termio {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            r#"
+            @element "success" {
+                color: green;
+            }
+            @element "dim" {
+                decoration: faint;
+            }
+            "#,
+        )
+        .unwrap();
+        tcss.with_color_policy(ColorPolicy::Always)
+    }
+
+    #[test]
+    fn test_markup() {
+        let tcss = sheet();
+        let text = tcss
+            .markup("Build <success>passed <dim>in 3.2s</dim></success>!")
+            .unwrap();
+        assert_eq!(text.plain_text(), "Build passed in 3.2s!");
+        assert_eq!(
+            text.to_string(),
+            "Build \x1b[32mpassed \x1b[2min 3.2s\x1b[22;39m!"
+        );
+
+        // Escapes and a `<` that is no tag are literal
+        let text = tcss.markup(r"\<dim> a < b \\").unwrap();
+        assert_eq!(text.to_string(), r"<dim> a < b \");
+        let name = escape_markup("<success>");
+        assert_eq!(tcss.markup(&name).unwrap().plain_text(), "<success>");
+    }
+
+    #[test]
+    fn test_markup_errors() {
+        let tcss = sheet();
+        assert_eq!(
+            tcss.markup("a <warn>b</warn>").unwrap_err(),
+            MarkupError::UnknownTag {
+                name: "warn".to_string(),
+                offset: 2
+            }
+        );
+
+        let (text, errors) = tcss.markup_recovering("<success>a<dim>b</success></dim><dim>c");
+        assert_eq!(text.plain_text(), "abc");
+        assert_eq!(
+            errors,
+            vec![
+                MarkupError::MismatchedTag {
+                    expected: "dim".to_string(),
+                    found: "success".to_string(),
+                    offset: 16
+                },
+                MarkupError::UnexpectedClosingTag {
+                    name: "dim".to_string(),
+                    offset: 26
+                },
+                MarkupError::UnclosedTag {
+                    name: "dim".to_string(),
+                    offset: 32
+                },
+            ]
+        );
+    }
+}
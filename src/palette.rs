@@ -0,0 +1,105 @@
+use crate::color::Color;
+use crate::style::Style;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Concrete RGB values for the basic colors and color codes.
+///
+/// Terminals let users redefine these colors, so `Color::Blue` may be
+/// anything from navy to sky blue. A palette pins them down: with palette
+/// remapping enabled on a `Termio`, colors with an entry are written as
+/// truecolor instead of relying on the terminal's palette.
+///
+/// ```
+/// use termio::{Color, Palette};
+///
+/// let palette = Palette::new().with(Color::Blue, Color::rgb(95, 175, 255));
+/// assert_eq!(palette.remap(Color::Blue), Color::Rgb(95, 175, 255));
+/// assert_eq!(palette.remap(Color::Red), Color::Red);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Palette {
+    /// RGB values by xterm index; the basic colors are 0 to 15
+    colors: BTreeMap<u8, (u8, u8, u8)>,
+}
+
+impl Palette {
+    /// Creates a palette without any entries.
+    pub fn new() -> Self {
+        Palette::default()
+    }
+
+    /// Returns the Okabe-Ito palette for the eight basic colors, whose hues
+    /// stay distinct with protanopia, deuteranopia and tritanopia.
+    ///
+    /// Red becomes vermillion, green bluish green and cyan sky blue; white
+    /// and black are kept pure. Bright colors keep the terminal's values.
+    pub fn okabe_ito() -> Self {
+        Palette::new()
+            .with(Color::Black, Color::Rgb(0, 0, 0))
+            .with(Color::Red, Color::Rgb(213, 94, 0))
+            .with(Color::Green, Color::Rgb(0, 158, 115))
+            .with(Color::Yellow, Color::Rgb(240, 228, 66))
+            .with(Color::Blue, Color::Rgb(0, 114, 178))
+            .with(Color::Magenta, Color::Rgb(204, 121, 167))
+            .with(Color::Cyan, Color::Rgb(86, 180, 233))
+            .with(Color::White, Color::Rgb(255, 255, 255))
+    }
+
+    /// Maps `color`, a basic color or color code, to the RGB value of `value`.
+    ///
+    /// RGB colors have no palette entry and are left unchanged.
+    pub fn with(mut self, color: Color, value: Color) -> Self {
+        self.set(color, value);
+        self
+    }
+
+    /// Like `with`, but in place.
+    pub fn set(&mut self, color: Color, value: Color) {
+        if let Some(index) = color.index() {
+            self.colors.insert(index, value.to_rgb());
+        }
+    }
+
+    /// Returns the RGB value `color` is mapped to, if it has an entry.
+    pub fn get(&self, color: Color) -> Option<Color> {
+        let (r, g, b) = *self.colors.get(&color.index()?)?;
+        Some(Color::Rgb(r, g, b))
+    }
+
+    /// Returns the RGB value of `color` if it has an entry, `color` otherwise.
+    pub fn remap(&self, color: Color) -> Color {
+        self.get(color).unwrap_or(color)
+    }
+
+    /// Returns a copy of `style` with every color remapped.
+    pub fn apply(&self, style: &Style) -> Style {
+        style.map_colors(|c| self.remap(c))
+    }
+
+    /// Returns whether the palette has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Adds the entries of `other`, replacing existing ones.
+    pub fn extend(&mut self, other: &Palette) {
+        self.colors.extend(&other.colors);
+    }
+}
+
+impl fmt::Display for Palette {
+    /// Formats the palette as a TCSS `@palette` block.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "@palette {{")?;
+        for (index, (r, g, b)) in &self.colors {
+            writeln!(
+                f,
+                "    {}: {};",
+                Color::from_index(*index),
+                Color::Rgb(*r, *g, *b)
+            )?;
+        }
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,1292 @@
+//! Line-oriented TCSS parser.
+//!
+//! Every error produced here carries a [`Diagnostic`] pointing at the
+//! offending part of the source line.
+
+use crate::border::{BorderChars, BorderStyle};
+use crate::color::Color;
+use crate::condition::{Clause, Condition};
+use crate::decoration::Decoration;
+use crate::diagnostic::{Diagnostic, ParseError};
+use crate::gradient::Gradient;
+use crate::import::{normalize, FileResolver, ImportResolver};
+use crate::style::Style;
+use crate::termio::{ConditionalStyle, Termio};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A single source line together with its position in the source.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    number: usize,
+    offset: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    /// Builds an `InvalidSyntax` error pointing at `part`, a subslice of this line.
+    fn error(&self, part: &str, message: impl Into<String>) -> ParseError {
+        ParseError::InvalidSyntax(Box::new(self.diagnostic(part, message)))
+    }
+
+    fn diagnostic(&self, part: &str, message: impl Into<String>) -> Diagnostic {
+        let start = part.as_ptr() as usize - self.text.as_ptr() as usize;
+        Diagnostic::new(
+            message,
+            self.text,
+            self.number,
+            self.offset,
+            start..start + part.len(),
+        )
+    }
+}
+
+/// An `@element` block that has been opened but not yet stored.
+struct Element<'a> {
+    name: &'a str,
+    /// Parent names from an `extends` clause, in declaration order
+    parents: Vec<&'a str>,
+    /// Condition of the enclosing `@when` blocks, if any
+    condition: Option<Condition>,
+    /// Name of the enclosing `@theme` block, if any
+    theme: Option<&'a str>,
+    line: Line<'a>,
+    style: Style,
+}
+
+impl<'a> Element<'a> {
+    fn key(&self) -> (Option<&'a str>, &'a str) {
+        (self.theme, self.name)
+    }
+}
+
+/// An open `@when` or `@theme` block.
+struct Scope<'a> {
+    line: Line<'a>,
+    header: &'a str,
+    /// Combined condition of this block and all enclosing ones
+    condition: Option<Condition>,
+    /// Theme of this block or an enclosing one
+    theme: Option<&'a str>,
+}
+
+pub(crate) struct Parser<'a> {
+    source: &'a str,
+    /// File the source was read from; imports are resolved relative to it
+    path: Option<&'a Path>,
+    /// Sheets currently being parsed, outermost first, for cycle detection
+    imports: Vec<PathBuf>,
+    /// Sheets imported anywhere in this parse, which are not parsed again
+    imported: HashSet<PathBuf>,
+    /// Collect errors and keep going instead of stopping at the first one
+    recover: bool,
+    errors: Vec<ParseError>,
+    /// Stored elements whose `extends` clause still has to be resolved
+    pending: Vec<Element<'a>>,
+    /// Open `@when` and `@theme` blocks, outermost first
+    scopes: Vec<Scope<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        Parser {
+            source,
+            path: None,
+            imports: Vec::new(),
+            imported: HashSet::new(),
+            recover: false,
+            errors: Vec::new(),
+            pending: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Sets the file the source was read from.
+    pub(crate) fn with_path(mut self, path: &'a Path) -> Self {
+        self.path = Some(path);
+        self.imports = vec![normalize(path)];
+        self
+    }
+
+    /// Enables recovery: on error the parser skips to the next property
+    /// or closing `}` and records the error instead of returning it.
+    pub(crate) fn recovering(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Returns the errors collected while recovering.
+    pub(crate) fn into_errors(self) -> Vec<ParseError> {
+        self.errors
+    }
+
+    /// Parses the whole source, inserting every element and variable into `termio`.
+    pub(crate) fn parse(&mut self, termio: &mut Termio) -> Result<(), ParseError> {
+        let mut current: Option<Element> = None;
+        // Opening line of the `@palette` block being parsed
+        let mut palette: Option<Line> = None;
+        // Nesting depth of an invalid block whose body is being skipped
+        let mut skipping = 0;
+
+        for line in self.lines() {
+            let text = line.text.trim();
+            // Skip empty lines and comments
+            if text.is_empty() || text.starts_with("//") {
+                continue;
+            }
+
+            if skipping > 0 {
+                if text.ends_with('{') {
+                    skipping += 1;
+                } else if text == "}" {
+                    skipping -= 1;
+                }
+            } else if palette.is_some() {
+                if text == "}" {
+                    palette = None;
+                } else {
+                    let result = Self::palette_entry(termio, &line, text);
+                    self.report(result)?;
+                }
+            } else if let Some(rest) = text.strip_prefix("@element") {
+                if let Some(element) = current.take() {
+                    let result = self.insert(termio, element);
+                    self.report(result)?;
+                }
+                match Self::element_header(&line, text, rest) {
+                    Ok(mut element) => {
+                        if let Some(scope) = self.scopes.last() {
+                            element.condition = scope.condition.clone();
+                            element.theme = scope.theme;
+                        }
+                        current = Some(element);
+                    }
+                    Err(e) => {
+                        skipping = 1;
+                        self.report(Err(e))?;
+                    }
+                }
+            } else if let Some(element) = &mut current {
+                if text == "}" {
+                    let result = self.insert(termio, current.take().unwrap());
+                    self.report(result)?;
+                } else {
+                    let result = Self::parse_property(termio, &line, text, &mut element.style);
+                    self.report(result)?;
+                }
+            } else if text == "}" && !self.scopes.is_empty() {
+                self.scopes.pop();
+            } else if let Some(rest) = text.strip_prefix("@when") {
+                let result = self.open_when(&line, text, rest);
+                if result.is_err() {
+                    skipping = 1;
+                }
+                self.report(result)?;
+            } else if let Some(rest) = text.strip_prefix("@theme") {
+                let result = self.open_theme(&line, text, rest);
+                if result.is_err() {
+                    skipping = 1;
+                }
+                self.report(result)?;
+            } else if let Some(scope) = self.scopes.last() {
+                // Themes cannot be nested, not even through a @when block
+                let message = if scope.theme.is_some() {
+                    "Only @element and @when blocks are allowed inside @theme"
+                } else {
+                    "Only @element, @when and @theme blocks are allowed inside @when"
+                };
+                self.report(Err(line.error(text, message)))?;
+            } else if let Some(rest) = text.strip_prefix("@palette") {
+                if rest.trim() == "{" {
+                    palette = Some(line);
+                } else {
+                    let err = line.error(text, "Expected `@palette {`");
+                    self.report(Err(err))?;
+                }
+            } else if let Some(rest) = text.strip_prefix("@import") {
+                let result = self.import(termio, &line, text, rest);
+                self.report(result)?;
+            } else if let Some(rest) = text.strip_prefix("@var") {
+                let result = Self::define_variable(termio, &line, rest);
+                self.report(result)?;
+            } else if text.starts_with("--") {
+                let result = Self::define_variable(termio, &line, text);
+                self.report(result)?;
+            } else {
+                self.report(Err(line.error(text, "Expected an @element block")))?;
+            }
+        }
+
+        // Handle the last style if exists
+        if let Some(element) = current {
+            let result = self.insert(termio, element);
+            self.report(result)?;
+        }
+
+        if let Some(line) = palette {
+            let err = line.error(line.text.trim(), "Unclosed @palette block");
+            self.report(Err(err))?;
+        }
+
+        for scope in std::mem::take(&mut self.scopes) {
+            let kind = scope.header.split_whitespace().next().unwrap_or_default();
+            let err = scope
+                .line
+                .error(scope.header, format!("Unclosed {} block", kind));
+            self.report(Err(err))?;
+        }
+
+        self.resolve_inheritance(termio)
+    }
+
+    /// Passes `result` through, or records its error when recovering.
+    fn report(&mut self, result: Result<(), ParseError>) -> Result<(), ParseError> {
+        match result {
+            Err(e) if self.recover => {
+                self.errors.push(e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Opens a `@when <clause> [and <clause>...] {` block.
+    fn open_when(
+        &mut self,
+        line: &Line<'a>,
+        text: &'a str,
+        rest: &'a str,
+    ) -> Result<(), ParseError> {
+        let query = rest
+            .trim()
+            .strip_suffix('{')
+            .ok_or_else(|| line.error(text, "Expected `{` after @when condition"))?
+            .trim();
+        if query.is_empty() {
+            return Err(line.error(text, "Missing @when condition"));
+        }
+
+        let clauses = query
+            .split(" and ")
+            .map(|clause| {
+                let clause = clause.trim();
+                Clause::from_str(clause).map_err(|e| line.error(clause, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let condition = Condition::new(clauses);
+        let parent = self.scopes.last();
+        let theme = parent.and_then(|scope| scope.theme);
+        let condition = match parent.and_then(|scope| scope.condition.as_ref()) {
+            Some(outer) => outer.and(&condition),
+            None => condition,
+        };
+
+        self.scopes.push(Scope {
+            line: *line,
+            header: text,
+            condition: Some(condition),
+            theme,
+        });
+        Ok(())
+    }
+
+    /// Opens a `@theme "name" {` block.
+    fn open_theme(
+        &mut self,
+        line: &Line<'a>,
+        text: &'a str,
+        rest: &'a str,
+    ) -> Result<(), ParseError> {
+        let (name, tail) =
+            Self::quoted(rest).ok_or_else(|| line.error(text, "Missing theme name"))?;
+        if tail.trim() != "{" {
+            return Err(line.error(text, "Expected `{` after @theme name"));
+        }
+        let parent = self.scopes.last();
+        if parent.is_some_and(|scope| scope.theme.is_some()) {
+            return Err(line.error(text, "@theme blocks cannot be nested"));
+        }
+
+        self.scopes.push(Scope {
+            line: *line,
+            header: text,
+            condition: parent.and_then(|scope| scope.condition.clone()),
+            theme: Some(name),
+        });
+        Ok(())
+    }
+
+    /// Parses the sheet referenced by an `@import "name";` line into `termio`.
+    fn import(
+        &mut self,
+        termio: &mut Termio,
+        line: &Line<'a>,
+        text: &'a str,
+        rest: &'a str,
+    ) -> Result<(), ParseError> {
+        let (name, _) =
+            Self::quoted(rest).ok_or_else(|| line.error(text, "Missing import path"))?;
+        let (path, content) = match termio.resolver.clone() {
+            Some(resolver) => resolver.resolve(&unescape(name), self.path),
+            None => FileResolver.resolve(&unescape(name), self.path),
+        }
+        .map_err(|e| line.error(name, e))?;
+
+        if let Some(start) = self.imports.iter().position(|p| *p == path) {
+            let cycle: Vec<String> = self.imports[start..]
+                .iter()
+                .chain(Some(&path))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(line.error(name, format!("Import cycle: {}", cycle.join(" -> "))));
+        }
+        // A sheet shared by several imports, such as a common base, is
+        // only parsed the first time
+        if !self.imported.insert(path.clone()) {
+            return Ok(());
+        }
+
+        termio.add_source(&path);
+        let mut parser = Parser::new(&content).with_path(&path);
+        parser.imports = self
+            .imports
+            .iter()
+            .cloned()
+            .chain(Some(path.clone()))
+            .collect();
+        parser.imported = std::mem::take(&mut self.imported);
+        parser.recover = self.recover;
+        let result = parser.parse(termio);
+        self.imported = parser.imported;
+        self.errors
+            .extend(parser.errors.into_iter().map(|e| e.with_path(&path)));
+        result.map_err(|e| e.with_path(&path))
+    }
+
+    /// Splits the source into lines, keeping track of their byte offsets.
+    fn lines(&self) -> impl Iterator<Item = Line<'a>> {
+        let mut offset = 0;
+        self.source.split('\n').enumerate().map(move |(i, raw)| {
+            let line = Line {
+                number: i + 1,
+                offset,
+                text: raw.strip_suffix('\r').unwrap_or(raw),
+            };
+            offset += raw.len() + 1;
+            line
+        })
+    }
+
+    /// Parses an `@element "name" [extends "parent", ...] {` header.
+    fn element_header(
+        line: &Line<'a>,
+        text: &'a str,
+        rest: &'a str,
+    ) -> Result<Element<'a>, ParseError> {
+        let (name, rest) =
+            Self::quoted(rest).ok_or_else(|| line.error(text, "Missing element name"))?;
+        let mut parents = Vec::new();
+
+        if let Some(list) = rest.trim_start().strip_prefix("extends") {
+            let list = list.trim().trim_end_matches('{').trim_end();
+            for parent in list.split(',') {
+                let parent = Self::quoted(parent)
+                    .filter(|(_, tail)| tail.trim().is_empty())
+                    .map(|(name, _)| name)
+                    .ok_or_else(|| {
+                        line.error(
+                            parent.trim(),
+                            format!("Expected a quoted parent name, found `{}`", parent.trim()),
+                        )
+                    })?;
+                parents.push(parent);
+            }
+        }
+
+        Ok(Element {
+            name,
+            parents,
+            condition: None,
+            theme: None,
+            line: *line,
+            style: Style::new(),
+        })
+    }
+
+    /// Splits `"name" rest` into the name as written, with its `\"` and `\\`
+    /// escapes, and the remainder.
+    fn quoted(s: &'a str) -> Option<(&'a str, &'a str)> {
+        let s = s.trim_start().strip_prefix('"')?;
+        let mut escaped = false;
+        for (i, c) in s.char_indices() {
+            match c {
+                '"' if !escaped => return Some((&s[..i], &s[i + 1..])),
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        None
+    }
+
+    /// Returns a property value with its quotes and escapes removed, if quoted.
+    fn string_value(value: &str) -> Cow<'_, str> {
+        match Parser::quoted(value) {
+            Some((raw, "")) => unescape(raw),
+            _ => Cow::Borrowed(value),
+        }
+    }
+
+    fn insert(&mut self, termio: &mut Termio, mut element: Element<'a>) -> Result<(), ParseError> {
+        if let Some(condition) = element.condition {
+            if let Some(parent) = element.parents.first() {
+                return Err(element
+                    .line
+                    .error(parent, "extends is not supported inside @when blocks"));
+            }
+            termio.conditional.push(ConditionalStyle {
+                condition,
+                theme: element.theme.map(|theme| unescape(theme).into_owned()),
+                name: unescape(element.name).into_owned(),
+                style: element.style,
+            });
+            return Ok(());
+        }
+
+        let theme = element.theme.map(|theme| unescape(theme).into_owned());
+        let name = unescape(element.name).into_owned();
+        let styles = match &theme {
+            Some(theme) => termio.themes.entry(theme.clone()).or_default(),
+            None => &mut termio.styles,
+        };
+        if styles.contains_key(&name) {
+            return Err(ParseError::DuplicateElement {
+                diagnostic: Box::new(
+                    element
+                        .line
+                        .diagnostic(element.name, format!("Duplicate element name: {}", name)),
+                ),
+                name,
+            });
+        }
+        let style = std::mem::take(&mut element.style);
+        styles.insert(name.clone(), style);
+        if !element.parents.is_empty() {
+            let key = (theme, name);
+            let parents = element
+                .parents
+                .iter()
+                .map(|p| unescape(p).into_owned())
+                .collect();
+            termio.parents.insert(key, parents);
+            self.pending.push(element);
+        }
+        Ok(())
+    }
+
+    /// Checks that the parents of every pending element exist and do not
+    /// form a cycle.
+    ///
+    /// Parents may come from this source or from anything parsed into
+    /// `termio` before. The styles themselves are merged when they are
+    /// looked up, see `Termio::inherited`.
+    fn resolve_inheritance(&mut self, termio: &mut Termio) -> Result<(), ParseError> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut resolved = Vec::new();
+        for element in &pending {
+            self.resolve(element, &pending, termio, &mut resolved, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn resolve(
+        &mut self,
+        element: &Element<'a>,
+        pending: &[Element<'a>],
+        termio: &mut Termio,
+        resolved: &mut Vec<(Option<&'a str>, &'a str)>,
+        stack: &mut Vec<(Option<&'a str>, &'a str)>,
+    ) -> Result<(), ParseError> {
+        if resolved.contains(&element.key()) {
+            return Ok(());
+        }
+        stack.push(element.key());
+
+        // Keys hold the names as written, lookups in `termio` unescaped ones
+        let theme = element.theme.map(unescape);
+        let name = unescape(element.name);
+        for &parent in &element.parents {
+            let parent_name = unescape(parent);
+            let themed = termio
+                .parent_theme(theme.as_deref(), &name, &parent_name)
+                .is_some();
+            let key = (element.theme.filter(|_| themed), parent);
+
+            if let Some(start) = stack.iter().position(|k| *k == key) {
+                let cycle: Vec<&str> = stack[start..].iter().map(|(_, name)| *name).collect();
+                let err = element.line.error(
+                    parent,
+                    format!("Inheritance cycle: {} -> {}", cycle.join(" -> "), parent),
+                );
+                self.report(Err(err))?;
+                continue;
+            }
+            if let Some(parent_element) = pending.iter().find(|e| e.key() == key) {
+                self.resolve(parent_element, pending, termio, resolved, stack)?;
+            }
+            let parent_theme = key.0.map(unescape);
+            if termio
+                .element(parent_theme.as_deref(), &parent_name)
+                .is_none()
+            {
+                let err = element.line.error(
+                    parent,
+                    format!("Unknown parent element `{}` of `{}`", parent_name, name),
+                );
+                self.report(Err(err))?;
+            }
+        }
+
+        stack.pop();
+        resolved.push(element.key());
+        Ok(())
+    }
+
+    /// Parses a `@var name: value;` or `--name: value;` definition.
+    fn define_variable(
+        termio: &mut Termio,
+        line: &Line<'a>,
+        text: &'a str,
+    ) -> Result<(), ParseError> {
+        let (name, value) = Self::split_property(line, text)?;
+        let key = name.strip_prefix("--").unwrap_or(name);
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(line.error(name, format!("Invalid variable name: {}", name)));
+        }
+        let value = Self::expand_variables(termio, line, value)?;
+        termio.variables.insert(key.to_string(), value.into_owned());
+        Ok(())
+    }
+
+    /// Parses a `<basic color or code>: <color>;` line of a `@palette` block.
+    fn palette_entry(
+        termio: &mut Termio,
+        line: &Line<'a>,
+        text: &'a str,
+    ) -> Result<(), ParseError> {
+        let (name, value) = Self::split_property(line, text)?;
+        let color = Color::from_str(name)
+            .ok()
+            .filter(|c| !matches!(c, Color::Rgb(..)))
+            .ok_or_else(|| {
+                line.error(
+                    name,
+                    format!("Expected a basic color or color code, found {}", name),
+                )
+            })?;
+        let expanded = Self::expand_variables(termio, line, value)?;
+        let value = Color::from_str(&expanded).map_err(|e| line.error(value, e))?;
+        termio.palette.set(color, value);
+        Ok(())
+    }
+
+    /// Splits `name: value;` into its trimmed name and value, without the
+    /// one terminating `;`.
+    fn split_property<'l>(line: &Line, text: &'l str) -> Result<(&'l str, &'l str), ParseError> {
+        let (name, value) = text
+            .split_once(':')
+            .ok_or_else(|| line.error(text, format!("Invalid property: {}", text)))?;
+        let value = value.trim();
+        Ok((
+            name.trim(),
+            value.strip_suffix(';').unwrap_or(value).trim_end(),
+        ))
+    }
+
+    /// Replaces every `var(--name)` reference in `value` with the variable's value.
+    fn expand_variables<'l>(
+        termio: &Termio,
+        line: &Line,
+        value: &'l str,
+    ) -> Result<Cow<'l, str>, ParseError> {
+        if !value.contains("var(") {
+            return Ok(Cow::Borrowed(value));
+        }
+
+        let mut expanded = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("var(") {
+            expanded.push_str(&rest[..start]);
+            let reference = &rest[start..];
+            let end = reference
+                .find(')')
+                .ok_or_else(|| line.error(reference, "Unterminated var() reference"))?;
+            let reference = &reference[..=end];
+            let name = reference[4..end].trim();
+            let value = termio
+                .var(name)
+                .ok_or_else(|| line.error(reference, format!("Undefined variable: {}", name)))?;
+            expanded.push_str(value);
+            rest = &rest[start + end + 1..];
+        }
+        expanded.push_str(rest);
+
+        Ok(Cow::Owned(expanded))
+    }
+
+    /// Parses a single `property: value;` line into `style`, expanding
+    /// variable references first.
+    fn parse_property(
+        termio: &Termio,
+        line: &Line<'a>,
+        text: &'a str,
+        style: &mut Style,
+    ) -> Result<(), ParseError> {
+        let (property, value) = Self::split_property(line, text)?;
+        match Self::expand_variables(termio, line, value)? {
+            Cow::Borrowed(_) => Self::apply_property(line, text, style),
+            Cow::Owned(expanded) => {
+                // Errors in the expanded text are reported at the original line
+                let text = format!("{}: {}", property, expanded);
+                let expanded_line = Line {
+                    text: &text,
+                    ..*line
+                };
+                Self::apply_property(&expanded_line, &text, style).map_err(|e| {
+                    let message = e.diagnostic().map_or(String::new(), |d| d.message.clone());
+                    let in_property = e
+                        .diagnostic()
+                        .is_some_and(|d| d.span.start < line.offset + property.len());
+                    line.error(if in_property { property } else { value }, message)
+                })
+            }
+        }
+    }
+
+    /// Applies a single `property: value;` line to `style`.
+    fn apply_property(line: &Line, text: &str, style: &mut Style) -> Result<(), ParseError> {
+        let (property, value) = Self::split_property(line, text)?;
+
+        match property {
+            "color" if value.starts_with("linear-gradient") => {
+                style.fg_gradient = Some(Self::parse_gradient(line, value)?);
+                style.fg = None;
+            }
+            "background" if value.starts_with("linear-gradient") => {
+                style.bg_gradient = Some(Self::parse_gradient(line, value)?);
+                style.bg = None;
+            }
+            "color" => {
+                style.fg = Some(Self::parse_color(line, value)?);
+                style.fg_gradient = None;
+            }
+            "background" => {
+                style.bg = Some(Self::parse_color(line, value)?);
+                style.bg_gradient = None;
+            }
+            "decoration" => style.decoration = Some(Self::parse_decoration(line, value)?),
+            "text-decoration-style" => {
+                style.underline_style =
+                    Some(Decoration::underline_style(value).map_err(|e| line.error(value, e))?)
+            }
+            "link" => {
+                let url = Self::string_value(value);
+                if url.is_empty() || url.chars().any(char::is_control) {
+                    return Err(line.error(value, format!("Invalid link: {}", value)));
+                }
+                style.link = Some(url.to_string());
+            }
+            "underline-color" => style.underline_color = Some(Self::parse_color(line, value)?),
+            "padding" => {
+                let [top, right, bottom, left] = Self::parse_sides(line, "padding", value)?;
+                if value.split_whitespace().count() == 1 {
+                    style.padding = Some(top);
+                }
+                style.padding_top = Some(top);
+                style.padding_right = Some(right);
+                style.padding_bottom = Some(bottom);
+                style.padding_left = Some(left);
+            }
+            "padding-top" => style.padding_top = Some(Self::parse_u8(line, property, value)?),
+            "padding-bottom" => style.padding_bottom = Some(Self::parse_u8(line, property, value)?),
+            "padding-left" => style.padding_left = Some(Self::parse_u8(line, property, value)?),
+            "padding-right" => style.padding_right = Some(Self::parse_u8(line, property, value)?),
+            "margin" => {
+                let [top, right, bottom, left] = Self::parse_sides(line, "margin", value)?;
+                if value.split_whitespace().count() == 1 {
+                    style.margin = Some(top);
+                }
+                style.margin_top = Some(top);
+                style.margin_right = Some(right);
+                style.margin_bottom = Some(bottom);
+                style.margin_left = Some(left);
+            }
+            "margin-top" => style.margin_top = Some(Self::parse_u8(line, property, value)?),
+            "margin-bottom" => style.margin_bottom = Some(Self::parse_u8(line, property, value)?),
+            "margin-left" => style.margin_left = Some(Self::parse_u8(line, property, value)?),
+            "margin-right" => style.margin_right = Some(Self::parse_u8(line, property, value)?),
+            "border-color" => style.border_color = Some(Self::parse_color(line, value)?),
+            "border-style" => {
+                style.border_style = Some(Self::parse_border_style(line, value)?);
+                style.border_chars = None;
+            }
+            "border-chars" => {
+                let chars = Self::string_value(value);
+                style.border_chars =
+                    Some(BorderChars::from_str(&chars).map_err(|e| line.error(value, e))?);
+                style.border_style = None;
+            }
+            "border" => {
+                let (s, c) = value.split_once(' ').ok_or_else(|| {
+                    line.error(
+                        value,
+                        format!("Invalid border value: {}. Use `<style> <color>`", value),
+                    )
+                })?;
+                style.border_style = Some(Self::parse_border_style(line, s)?);
+                style.border_chars = None;
+                style.border_color = Some(Self::parse_color(line, c.trim())?);
+            }
+            _ => return Err(line.error(property, format!("Unknown property: {}", property))),
+        }
+
+        Ok(())
+    }
+
+    fn parse_color(line: &Line, value: &str) -> Result<Color, ParseError> {
+        Color::from_str(value).map_err(|e| line.error(value, e))
+    }
+
+    fn parse_gradient(line: &Line, value: &str) -> Result<Gradient, ParseError> {
+        Gradient::from_str(value).map_err(|e| line.error(value, e))
+    }
+
+    /// Parses a decoration string into a vector of decorations
+    fn parse_decoration(line: &Line, value: &str) -> Result<Vec<Decoration>, ParseError> {
+        value
+            .split_whitespace()
+            .map(|d| Decoration::from_str(d).map_err(|e| line.error(d, e)))
+            .collect()
+    }
+
+    /// Parses a border style string into a BorderStyle
+    fn parse_border_style(line: &Line, value: &str) -> Result<BorderStyle, ParseError> {
+        BorderStyle::from_str(value).map_err(|e| line.error(value, e))
+    }
+
+    fn parse_u8(line: &Line, property: &str, value: &str) -> Result<u8, ParseError> {
+        value
+            .parse()
+            .map_err(|_| line.error(value, format!("Invalid {} value: {}", property, value)))
+    }
+
+    /// Parses the 1, 2 or 4 value shorthand into `[top, right, bottom, left]`.
+    fn parse_sides(line: &Line, property: &str, value: &str) -> Result<[u8; 4], ParseError> {
+        let values = value
+            .split_whitespace()
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| line.error(v, format!("Invalid {} value: {}", property, value)))
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        match values[..] {
+            [all] => Ok([all; 4]),
+            [v, h] => Ok([v, h, v, h]),
+            [top, right, bottom, left] => Ok([top, right, bottom, left]),
+            _ => Err(line.error(
+                value,
+                format!("Invalid {} format. Use 1, 2, or 4 values", property),
+            )),
+        }
+    }
+}
+
+/// Resolves the `\"` and `\\` escapes of a quoted string; other
+/// backslashes are kept.
+fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let mut text = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next @ ('"' | '\\'))) => {
+                text.push(next);
+                chars.next();
+            }
+            _ => text.push(c),
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// Writes `text` as a quoted TCSS string, escaping `"` and `\`.
+pub(crate) fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::border::BorderStyle;
+    use crate::capabilities::{Capabilities, ColorDepth, ColorPolicy};
+    use crate::color::Color;
+    use crate::decoration::Decoration;
+    use crate::diagnostic::ParseError;
+    use crate::import::EmbeddedResolver;
+    use crate::styled_text::StyledText;
+    use crate::termio::Termio;
+
+    #[test]
+    fn test_invalid_value_location() {
+        let mut tcss = Termio::new();
+        let err = tcss
+            .parse("@element \"header\" {\n    color: blu;\n}\n")
+            .unwrap_err();
+        let diagnostic = err.diagnostic().unwrap();
+
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.column, 12);
+        assert_eq!(diagnostic.span, 31..34);
+        assert!(diagnostic.snippet().ends_with("  |            ^^^"));
+    }
+
+    #[test]
+    fn test_duplicate_element_location() {
+        let mut tcss = Termio::new();
+        let err = tcss
+            .parse("@element \"a\" {\n}\n@element \"a\" {\n}\n")
+            .unwrap_err();
+
+        match err {
+            ParseError::DuplicateElement { name, diagnostic } => {
+                assert_eq!(name, "a");
+                assert_eq!((diagnostic.line, diagnostic.column), (3, 11));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_recovering_collects_all_errors() {
+        let mut tcss = Termio::new();
+        let errors = tcss.parse_recovering(
+            "@element \"a\" {\n    color: blu;\n    background: red;\n    size: 3;\n}\n\
+             @element {\n    color: red;\n}\n\
+             @element \"a\" {\n}\n",
+        );
+
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|e| e.diagnostic().unwrap().line)
+            .collect();
+        assert_eq!(lines, vec![2, 4, 6, 9]);
+        assert_eq!(tcss.get_style("a").unwrap().bg, Some(Color::Red));
+    }
+
+    #[test]
+    fn test_extends_merges_parents_in_order() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"button\" extends \"base\", \"accent\" {\n    color: green;\n}\n\
+             @element \"base\" {\n    color: white;\n    background: black;\n    padding: 1;\n}\n\
+             @element \"accent\" {\n    background: blue;\n}\n",
+        )
+        .unwrap();
+
+        let button = tcss.get_style("button").unwrap();
+        assert_eq!(button.fg, Some(Color::Green));
+        assert_eq!(button.bg, Some(Color::Blue));
+        assert_eq!(button.padding_left, Some(1));
+    }
+
+    #[test]
+    fn test_extends_errors() {
+        let mut tcss = Termio::new();
+        let errors = tcss.parse_recovering(
+            "@element \"a\" extends \"b\" {\n}\n\
+             @element \"b\" extends \"a\" {\n}\n\
+             @element \"c\" extends \"missing\" {\n}\n",
+        );
+
+        let messages: Vec<&str> = errors
+            .iter()
+            .map(|e| e.diagnostic().unwrap().message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Inheritance cycle: a -> b -> a",
+                "Unknown parent element `missing` of `c`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut tcss = Termio::new();
+        tcss.set_var("brand", "magenta");
+        tcss.parse(
+            "@var accent: rgb(255, 128, 0);\n--brand: blue;\n\
+             @element \"a\" {\n    color: var(--accent);\n    border: solid var(brand);\n    \
+             background: darken(var(--accent), 20%);\n}\n",
+        )
+        .unwrap();
+
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!(a.fg, Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(a.border_color, Some(Color::Magenta));
+        assert_eq!(a.bg, Some(Color::Rgb(153, 77, 0)));
+    }
+
+    #[test]
+    fn test_palette() {
+        let source = "@palette {\n    blue: #5fafff;\n    208: rgb(255, 135, 0);\n}\n\
+                      @element \"a\" {\n    color: blue;\n    background: red;\n}\n";
+        let mut tcss = Termio::new();
+        tcss.parse(source).unwrap();
+        assert_eq!(tcss.get_style("a").unwrap().fg, Some(Color::Blue));
+
+        let tcss = tcss.with_palette_remapping(true);
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!(a.fg, Some(Color::Rgb(95, 175, 255)));
+        assert_eq!(a.bg, Some(Color::Red));
+        assert_eq!(
+            tcss.palette().get(Color::Code(208)),
+            Some(Color::Rgb(255, 135, 0))
+        );
+
+        let mut reparsed = Termio::new();
+        reparsed.parse(&tcss.to_tcss()).unwrap();
+        assert_eq!(reparsed.palette(), tcss.palette());
+
+        let mut tcss = Termio::new();
+        let errors = tcss.parse_recovering("@palette {\n    orange: #ff8800;\n    blue: #zz;\n");
+        let messages: Vec<&str> = errors
+            .iter()
+            .map(|e| e.diagnostic().unwrap().message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Expected a basic color or color code, found orange",
+                "Invalid hex color: #zz. Expected #rgb or #rrggbb with hex digits",
+                "Unclosed @palette block"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_underline_styles() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"error\" {\n    decoration: bold underline;\n    \
+             text-decoration-style: curly;\n    underline-color: #ff0000;\n}\n",
+        )
+        .unwrap();
+
+        let error = tcss.get_style("error").unwrap();
+        assert_eq!(
+            error.decoration,
+            Some(vec![Decoration::Bold, Decoration::Underline])
+        );
+        assert_eq!(error.underline_style, Some(Decoration::CurlyUnderline));
+        assert_eq!(error.underline_color, Some(Color::Rgb(255, 0, 0)));
+        assert!(error.to_tcss().contains(
+            "decoration: bold underline;\ntext-decoration-style: curly;\n\
+             underline-color: rgb(255, 0, 0);"
+        ));
+        let rendered = "typo"
+            .style(
+                "error",
+                &tcss.clone().with_color_policy(ColorPolicy::Always),
+            )
+            .to_string();
+        assert!(rendered.contains("\x1b[1;4:3;58;2;255;0;0mtypo"));
+
+        // The style neither adds an underline nor depends on the order
+        tcss.parse(
+            "@element \"warning\" {\n    text-decoration-style: dotted;\n    \
+             decoration: italic underline;\n}\n\
+             @element \"plain\" {\n    text-decoration-style: dashed;\n    decoration: bold;\n}\n",
+        )
+        .unwrap();
+        let colored = tcss.clone().with_color_policy(ColorPolicy::Always);
+        assert!("x"
+            .style("warning", &colored)
+            .to_string()
+            .contains("\x1b[3;4:4mx"));
+        assert!("x"
+            .style("plain", &colored)
+            .to_string()
+            .contains("\x1b[1mx"));
+
+        let err = tcss
+            .parse("@element \"x\" {\n    text-decoration-style: zigzag;\n}\n")
+            .unwrap_err();
+        assert_eq!(
+            err.diagnostic().unwrap().message,
+            "Unknown underline style: zigzag. Use solid, double, curly, dotted or dashed"
+        );
+    }
+
+    #[test]
+    fn test_link() {
+        let mut tcss = Termio::new();
+        tcss.parse("@element \"ticket\" {\n    link: \"https://tracker.example/{text}\";\n}\n")
+            .unwrap();
+        let ticket = tcss.get_style("ticket").unwrap();
+        assert_eq!(
+            ticket.link.as_deref(),
+            Some("https://tracker.example/{text}")
+        );
+        assert_eq!(
+            ticket.to_tcss(),
+            "link: \"https://tracker.example/{text}\";\n"
+        );
+
+        // Only the terminating semicolon is removed from the URL
+        tcss.parse(
+            "@element \"quoted\" {\n    link: \"https://x/a;b;\";\n}\n\
+             @element \"bare\" {\n    link: https://x/a;b;;\n}\n",
+        )
+        .unwrap();
+        for name in ["quoted", "bare"] {
+            let link = tcss.get_style(name).unwrap().link;
+            assert_eq!(link.as_deref(), Some("https://x/a;b;"));
+        }
+    }
+
+    #[test]
+    fn test_border_chars() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"card\" {\n    border-chars: \"╭─╮│╯─╰│\";\n}\n\
+             @element \"heavy\" {\n    border-chars: \"╭─╮│╯─╰│\";\n    border: heavy red;\n}\n",
+        )
+        .unwrap();
+        let card = tcss.get_style("card").unwrap();
+        assert_eq!(card.border_chars, Some(BorderStyle::Rounded.chars()));
+        assert_eq!(card.to_tcss(), "border-chars: \"╭─╮│╯─╰│\";\n");
+        let heavy = tcss.get_style("heavy").unwrap();
+        assert_eq!(heavy.border_style, Some(BorderStyle::Heavy));
+        assert_eq!(heavy.border_chars, None);
+
+        let error = tcss
+            .parse("@element \"bad\" {\n    border-chars: \"+-+\";\n}\n")
+            .unwrap_err();
+        assert!(error.to_string().contains("Use 8 characters"));
+    }
+
+    #[test]
+    fn test_gradients() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"banner\" {\n    color: linear-gradient(#ff0000, #0000ff);\n    \
+             background: linear-gradient(to bottom, black, i-black);\n}\n\
+             @element \"plain\" extends \"banner\" {\n    color: white;\n}\n",
+        )
+        .unwrap();
+
+        let banner = tcss.get_style("banner").unwrap();
+        let gradient = banner.fg_gradient.unwrap();
+        assert_eq!(
+            gradient.stops,
+            [Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255)]
+        );
+        assert!(banner.bg_gradient.is_some());
+        let plain = tcss.get_style("plain").unwrap();
+        assert_eq!((plain.fg, plain.fg_gradient), (Some(Color::White), None));
+        assert!(plain.bg_gradient.is_some());
+
+        let err = tcss
+            .parse("@element \"x\" {\n    color: linear-gradient(to middle, red, blue);\n}\n")
+            .unwrap_err();
+        assert_eq!(
+            (
+                err.diagnostic().unwrap().line,
+                err.diagnostic().unwrap().column
+            ),
+            (2, 12)
+        );
+    }
+
+    #[test]
+    fn test_undefined_variable_location() {
+        let mut tcss = Termio::new();
+        let err = tcss
+            .parse("@element \"a\" {\n    color: var(--nope);\n}\n")
+            .unwrap_err();
+        let diagnostic = err.diagnostic().unwrap();
+
+        assert_eq!(diagnostic.message, "Undefined variable: --nope");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 12));
+        assert_eq!(diagnostic.span.len(), "var(--nope)".len());
+    }
+
+    #[test]
+    fn test_import_relative_to_importer() {
+        let resolver = EmbeddedResolver::new()
+            .with("base.tcss", "@element \"base\" {\n    color: red;\n}\n")
+            .with(
+                "themes/dark.tcss",
+                "@import \"../base.tcss\";\n@element \"dark\" extends \"base\" {\n}\n",
+            );
+        let mut tcss = Termio::new().with_resolver(resolver);
+        tcss.parse("@import \"themes/dark.tcss\";\n").unwrap();
+
+        assert_eq!(tcss.get_style("dark").unwrap().fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn test_import_cycle() {
+        let resolver = EmbeddedResolver::new()
+            .with("a.tcss", "@import \"b.tcss\";\n")
+            .with("b.tcss", "\n@import \"a.tcss\";\n");
+        let mut tcss = Termio::new().with_resolver(resolver);
+        let err = tcss.parse("@import \"a.tcss\";\n").unwrap_err();
+        let diagnostic = err.diagnostic().unwrap();
+
+        assert_eq!(
+            diagnostic.message,
+            "Import cycle: a.tcss -> b.tcss -> a.tcss"
+        );
+        assert_eq!(
+            diagnostic.path.as_deref(),
+            Some(std::path::Path::new("b.tcss"))
+        );
+        assert_eq!(diagnostic.line, 2);
+    }
+
+    #[test]
+    fn test_diamond_import() {
+        let resolver = EmbeddedResolver::new()
+            .with("base.tcss", "@element \"base\" {\n    color: red;\n}\n")
+            .with(
+                "a.tcss",
+                "@import \"base.tcss\";\n@element \"a\" extends \"base\" {\n}\n",
+            )
+            .with(
+                "b.tcss",
+                "@import \"base.tcss\";\n@element \"b\" extends \"base\" {\n}\n",
+            );
+        let mut tcss = Termio::new().with_resolver(resolver);
+        tcss.parse("@import \"a.tcss\";\n@import \"b.tcss\";\n")
+            .unwrap();
+
+        assert_eq!(tcss.get_style("a").unwrap().fg, Some(Color::Red));
+        assert_eq!(tcss.get_style("b").unwrap().fg, Some(Color::Red));
+        assert_eq!(tcss.sources().len(), 3);
+    }
+
+    #[test]
+    fn test_when_blocks() {
+        let mut tcss = Termio::new().with_color_policy(ColorPolicy::Always);
+        tcss.parse(
+            "@element \"a\" {\n    color: rgb(255, 128, 0);\n    padding: 2;\n}\n\
+             @when color-depth < truecolor {\n    @element \"a\" {\n        color: yellow;\n    }\n}\n\
+             @when no-color {\n    @element \"a\" {\n        decoration: bold;\n    }\n}\n\
+             @when width < 80 and height >= 10 {\n    @element \"a\" {\n        padding: 0;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!((a.fg, a.padding), (Some(Color::Rgb(255, 128, 0)), Some(2)));
+
+        tcss.set_capabilities(
+            Capabilities::default()
+                .color_depth(ColorDepth::Ansi256)
+                .size(60, 20),
+        );
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!(
+            (a.fg, a.padding, a.decoration),
+            (Some(Color::Yellow), Some(0), None)
+        );
+
+        tcss.set_capabilities(Capabilities::default().color_depth(ColorDepth::NoColor));
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!(
+            (a.padding, a.decoration),
+            (Some(2), Some(vec![Decoration::Bold]))
+        );
+
+        // Colors turned off by the policy select `no-color` too
+        tcss.set_capabilities(Capabilities::default());
+        tcss.set_color_policy(ColorPolicy::Never);
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!(a.decoration, Some(vec![Decoration::Bold]));
+    }
+
+    #[test]
+    fn test_when_errors() {
+        let mut tcss = Termio::new();
+        let errors = tcss.parse_recovering(
+            "@when colour >= 256 {\n    @element \"a\" {\n    }\n}\n@when width < 80 {\n",
+        );
+
+        let messages: Vec<&str> = errors
+            .iter()
+            .map(|e| e.diagnostic().unwrap().message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["Unknown feature: colour", "Unclosed @when block"]
+        );
+    }
+
+    #[test]
+    fn test_scope_errors() {
+        let mut tcss = Termio::new();
+        let errors = tcss.parse_recovering(
+            "@when no-color {\n    @var a: red;\n    @theme \"dark\" {\n        @var b: red;\n    }\n}\n\
+             @theme \"light\" {\n    @when width < 80 {\n        @var c: red;\n    }\n}\n",
+        );
+
+        let messages: Vec<&str> = errors
+            .iter()
+            .map(|e| e.diagnostic().unwrap().message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Only @element, @when and @theme blocks are allowed inside @when",
+                "Only @element and @when blocks are allowed inside @theme",
+                "Only @element and @when blocks are allowed inside @theme",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_themes() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"text\" {\n    color: black;\n    padding: 1;\n}\n\
+             @element \"title\" {\n    color: blue;\n}\n\
+             @theme \"dark\" {\n\
+                 @element \"text\" extends \"text\" {\n        color: white;\n    }\n\
+                 @when no-color {\n        @element \"title\" {\n            decoration: bold;\n        }\n    }\n\
+             }\n",
+        )
+        .unwrap();
+
+        assert_eq!(tcss.themes(), vec!["dark"]);
+        assert_eq!(tcss.get_style("text").unwrap().fg, Some(Color::Black));
+
+        tcss.set_theme("dark");
+        let text = tcss.get_style("text").unwrap();
+        assert_eq!((text.fg, text.padding), (Some(Color::White), Some(1)));
+        assert_eq!(tcss.get_style("title").unwrap().fg, Some(Color::Blue));
+
+        tcss.set_capabilities(Capabilities::default().color_depth(ColorDepth::NoColor));
+        assert!(tcss.get_style("title").unwrap().decoration.is_some());
+        tcss.clear_theme();
+        assert!(tcss.get_style("title").unwrap().decoration.is_none());
+    }
+}
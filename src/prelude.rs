@@ -1,6 +1,10 @@
-pub use crate::border::BorderStyle;
+pub use crate::border::{BorderChars, BorderStyle};
+pub use crate::capabilities::{Capabilities, ColorDepth, ColorPolicy, Stream};
 pub use crate::color::Color;
+pub use crate::contrast::ContrastLevel;
 pub use crate::decoration::Decoration;
+pub use crate::palette::Palette;
 pub use crate::style::Style;
+pub use crate::vision::ColorBlindness;
 pub use crate::StyledText;
 pub use crate::Termio;
@@ -0,0 +1,245 @@
+use crate::capabilities::{Capabilities, ColorPolicy};
+use crate::diagnostic::ParseError;
+use crate::termio::Termio;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// A Termio that reloads itself when the files it was loaded from change.
+///
+/// Changes are detected by polling modification times, either explicitly
+/// with [`reload_if_changed`](Self::reload_if_changed) or from a background
+/// thread started with [`watch`](Self::watch). A successful reload swaps in
+/// the new styles atomically; a failed one keeps the previous styles and
+/// reports the error.
+///
+/// Clones share the same styles. Settings changed through the setters
+/// apply to the current styles right away and are kept across reloads.
+#[derive(Clone, Debug)]
+pub struct WatchedTermio {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    current: RwLock<Arc<Termio>>,
+    /// Held for a whole reload, so reloads finish in the order they start
+    reloading: Mutex<()>,
+    /// Modification time of every source as of the last (attempted) load
+    stamps: Mutex<Vec<(PathBuf, Option<SystemTime>)>>,
+}
+
+impl WatchedTermio {
+    /// Loads a TCSS file and starts tracking it and everything it imports.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ParseError> {
+        Self::with_settings(path, Termio::new())
+    }
+
+    /// Like `from_file`, but parses into `settings`, keeping its resolver,
+    /// capabilities and variables for every reload.
+    pub fn with_settings(path: impl AsRef<Path>, settings: Termio) -> Result<Self, ParseError> {
+        let path = path.as_ref().to_path_buf();
+        let mut termio = settings.with_same_settings();
+        termio.parse_file(&path)?;
+        let stamps = stamps(termio.sources());
+
+        Ok(WatchedTermio {
+            inner: Arc::new(Inner {
+                path,
+                current: RwLock::new(Arc::new(termio)),
+                reloading: Mutex::new(()),
+                stamps: Mutex::new(stamps),
+            }),
+        })
+    }
+
+    /// Returns the current styles.
+    ///
+    /// The snapshot stays valid while newer versions are loaded.
+    pub fn termio(&self) -> Arc<Termio> {
+        self.inner.current.read().unwrap().clone()
+    }
+
+    /// Switches to the definitions of `theme`, see `Termio::set_theme`.
+    pub fn set_theme(&self, theme: &str) {
+        self.update(|termio| termio.set_theme(theme));
+    }
+
+    /// Switches back to the un-themed definitions.
+    pub fn clear_theme(&self) {
+        self.update(Termio::clear_theme);
+    }
+
+    /// Sets the terminal capabilities `@when` blocks are evaluated against.
+    pub fn set_capabilities(&self, capabilities: Capabilities) {
+        self.update(|termio| termio.set_capabilities(capabilities));
+    }
+
+    /// Sets when styled strings write escape codes.
+    pub fn set_color_policy(&self, policy: ColorPolicy) {
+        self.update(|termio| termio.set_color_policy(policy));
+    }
+
+    /// Applies `f` to the current styles; reloads copy the settings from them.
+    ///
+    /// Snapshots returned by `termio` before the change keep the old settings.
+    fn update(&self, f: impl FnOnce(&mut Termio)) {
+        let mut current = self.inner.current.write().unwrap();
+        f(Arc::make_mut(&mut current));
+    }
+
+    /// Reloads if any source file changed since the last load.
+    ///
+    /// Returns whether new styles were loaded. On error the previous styles
+    /// stay active and the error is not reported again until a file changes.
+    pub fn reload_if_changed(&self) -> Result<bool, ParseError> {
+        let changed = {
+            let stamps = self.inner.stamps.lock().unwrap();
+            stamps.iter().any(|(path, time)| modified(path) != *time)
+        };
+        if !changed {
+            return Ok(false);
+        }
+        self.reload().map(|_| true)
+    }
+
+    /// Reparses the files unconditionally, swapping in the result on success.
+    pub fn reload(&self) -> Result<(), ParseError> {
+        let _reloading = self.inner.reloading.lock().unwrap();
+        let termio = self.parse()?;
+        self.swap(termio);
+        Ok(())
+    }
+
+    /// Parses the files into a new Termio and tracks their sources.
+    fn parse(&self) -> Result<Termio, ParseError> {
+        let current = self.termio();
+        let mut termio = current.with_same_settings();
+        let result = termio.parse_file(&self.inner.path);
+
+        // Track the files of both versions, so fixing a broken import
+        // or removing it triggers another reload
+        let mut sources = current.sources().to_vec();
+        for source in termio.sources() {
+            if !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+        *self.inner.stamps.lock().unwrap() = stamps(&sources);
+
+        result.map(|_| termio)
+    }
+
+    /// Swaps in newly parsed styles with the settings current at this point,
+    /// so setters called during the parse are not lost.
+    fn swap(&self, mut termio: Termio) {
+        let mut current = self.inner.current.write().unwrap();
+        termio.copy_settings(&current);
+        *current = Arc::new(termio);
+    }
+
+    /// Starts a background thread that checks for changes every `interval`.
+    ///
+    /// `on_error` is called with the error of each failed reload. The thread
+    /// stops once every `WatchedTermio` handle has been dropped.
+    pub fn watch<F>(&self, interval: Duration, on_error: F) -> JoinHandle<()>
+    where
+        F: Fn(ParseError) + Send + 'static,
+    {
+        let inner: Weak<Inner> = Arc::downgrade(&self.inner);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            if let Err(e) = (WatchedTermio { inner }).reload_if_changed() {
+                on_error(e);
+            }
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn stamps(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), modified(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchedTermio;
+    use crate::capabilities::ColorPolicy;
+    use crate::color::Color;
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+
+    /// Writes `content` with an mtime `tick` seconds after a fixed point in
+    /// the past, so each change is seen even with coarse timestamps.
+    fn write(path: &std::path::Path, content: &str, tick: u64) {
+        fs::write(path, content).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(100 - tick);
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = std::env::temp_dir().join(format!("termio-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.tcss");
+        let base = dir.join("base.tcss");
+        write(&base, "@element \"a\" {\n    color: red;\n}\n", 0);
+        write(&main, "@import \"base.tcss\";\n", 0);
+
+        let watched = WatchedTermio::from_file(&main).unwrap();
+        assert!(!watched.reload_if_changed().unwrap());
+        let before = watched.termio();
+
+        write(&base, "@element \"a\" {\n    color: blu;\n}\n", 1);
+        assert!(watched.reload_if_changed().is_err());
+        assert!(!watched.reload_if_changed().unwrap());
+        assert_eq!(
+            watched.termio().get_style("a").unwrap().fg,
+            Some(Color::Red)
+        );
+
+        write(&base, "@element \"a\" {\n    color: blue;\n}\n", 2);
+        assert!(watched.reload_if_changed().unwrap());
+        assert_eq!(
+            watched.termio().get_style("a").unwrap().fg,
+            Some(Color::Blue)
+        );
+        assert_eq!(before.get_style("a").unwrap().fg, Some(Color::Red));
+
+        // Settings survive the next reload
+        watched.set_theme("dark");
+        assert_eq!(before.theme(), None);
+        write(&base, "@element \"a\" {\n    color: green;\n}\n", 3);
+        assert!(watched.reload_if_changed().unwrap());
+        assert_eq!(watched.termio().theme(), Some("dark"));
+
+        // A setter running between the parse and the swap is kept too
+        write(&base, "@element \"a\" {\n    color: yellow;\n}\n", 4);
+        let parsed = watched.parse().unwrap();
+        watched.set_color_policy(ColorPolicy::Never);
+        watched.clear_theme();
+        watched.swap(parsed);
+        let current = watched.termio();
+        assert_eq!(current.get_style("a").unwrap().fg, Some(Color::Yellow));
+        assert_eq!(current.color_policy(), ColorPolicy::Never);
+        assert_eq!(current.theme(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
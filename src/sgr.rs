@@ -0,0 +1,196 @@
+//! Tracking of the terminal's SGR (Select Graphic Rendition) state.
+//!
+//! Instead of resetting everything with `\x1b[0m` after each styled run,
+//! the renderer describes the attributes every run needs and [`SgrWriter`]
+//! emits only the codes that change them. Text rendered this way leaves
+//! attributes it did not set alone, so a styled string can be nested in
+//! output that is already styled.
+
+use crate::color::Color;
+use crate::decoration::Decoration;
+
+/// The SGR attributes active for a run of text.
+///
+/// Attributes that share an off code are kept in one field, so e.g. only
+/// one underline style or one blink speed is active at a time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SgrState {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub underline_color: Option<Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: Option<Decoration>, // Any of the underline styles
+    pub blink: Option<Decoration>,     // Blink or RapidBlink
+    pub reverse: bool,
+    pub conceal: bool,
+    pub crossed_out: bool,
+    pub frame: Option<Decoration>, // Framed or Encircled
+    pub overline: bool,
+}
+
+impl SgrState {
+    /// Turns a decoration on; `Decoration::None` turns all of them off.
+    pub fn decorate(&mut self, decoration: Decoration) {
+        match decoration {
+            Decoration::None => {
+                *self = SgrState {
+                    fg: self.fg,
+                    bg: self.bg,
+                    underline_color: self.underline_color,
+                    ..SgrState::default()
+                }
+            }
+            Decoration::Bold => self.bold = true,
+            Decoration::Faint => self.faint = true,
+            Decoration::Italic => self.italic = true,
+            Decoration::Blink | Decoration::RapidBlink => self.blink = Some(decoration),
+            Decoration::Reverse => self.reverse = true,
+            Decoration::Conceal | Decoration::Hidden => self.conceal = true,
+            Decoration::CrossedOut | Decoration::Strikethrough => self.crossed_out = true,
+            Decoration::Framed | Decoration::Encircled => self.frame = Some(decoration),
+            Decoration::Overline => self.overline = true,
+            decoration => self.underline = Some(decoration),
+        }
+    }
+}
+
+/// Writes the codes moving the terminal from one [`SgrState`] to another.
+#[derive(Debug, Default)]
+pub(crate) struct SgrWriter {
+    current: SgrState,
+}
+
+impl SgrWriter {
+    /// Writes the codes needed to make `target` the active state, if any.
+    pub fn transition(&mut self, output: &mut String, target: &SgrState) {
+        let current = &self.current;
+        if current == target {
+            return;
+        }
+        let mut params: Vec<String> = Vec::new();
+
+        // Bold and faint share their off code, so turn both off and
+        // restore the one that stays
+        let mut bold = current.bold;
+        let mut faint = current.faint;
+        if (bold && !target.bold) || (faint && !target.faint) {
+            params.push("22".to_string());
+            bold = false;
+            faint = false;
+        }
+        if target.bold && !bold {
+            params.push("1".to_string());
+        }
+        if target.faint && !faint {
+            params.push("2".to_string());
+        }
+
+        let mut flag = |on: bool, off: bool, on_code: &str, off_code: &str| {
+            if on != off {
+                params.push(if on { on_code } else { off_code }.to_string());
+            }
+        };
+        flag(target.italic, current.italic, "3", "23");
+        flag(target.reverse, current.reverse, "7", "27");
+        flag(target.conceal, current.conceal, "8", "28");
+        flag(target.crossed_out, current.crossed_out, "9", "29");
+        flag(target.overline, current.overline, "53", "55");
+
+        let mut choice = |on: Option<Decoration>, off: Option<Decoration>, off_code: &str| {
+            if on != off {
+                params.push(on.map_or(off_code, |d| d.sgr_code()).to_string());
+            }
+        };
+        choice(target.underline, current.underline, "24");
+        choice(target.blink, current.blink, "25");
+        choice(target.frame, current.frame, "54");
+
+        if target.fg != current.fg {
+            params.push(target.fg.map_or("39".to_string(), |c| c.sgr_foreground()));
+        }
+        if target.bg != current.bg {
+            params.push(target.bg.map_or("49".to_string(), |c| c.sgr_background()));
+        }
+        if target.underline_color != current.underline_color {
+            params.push(
+                target
+                    .underline_color
+                    .map_or("59".to_string(), |c| c.sgr_underline()),
+            );
+        }
+
+        output.push_str(&format!("\x1b[{}m", params.join(";")));
+        self.current = target.clone();
+    }
+
+    /// Writes the codes turning off everything this writer turned on.
+    pub fn finish(&mut self, output: &mut String) {
+        self.transition(output, &SgrState::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SgrState, SgrWriter};
+    use crate::capabilities::ColorPolicy;
+    use crate::color::Color;
+    use crate::decoration::Decoration;
+    use crate::styled_text::StyledText;
+
+    #[test]
+    fn test_minimal_transitions() {
+        let mut writer = SgrWriter::default();
+        let mut output = String::new();
+
+        let mut bold_red = SgrState::default();
+        bold_red.decorate(Decoration::Bold);
+        bold_red.fg = Some(Color::Red);
+        writer.transition(&mut output, &bold_red);
+        assert_eq!(output, "\x1b[1;31m");
+
+        // An unchanged state writes nothing
+        writer.transition(&mut output, &bold_red);
+        assert_eq!(output, "\x1b[1;31m");
+
+        // Dropping bold keeps faint by turning it back on
+        let mut faint_red = bold_red.clone();
+        faint_red.decorate(Decoration::Faint);
+        writer.transition(&mut output, &faint_red);
+        faint_red.bold = false;
+        output.clear();
+        writer.transition(&mut output, &faint_red);
+        assert_eq!(output, "\x1b[22;2m");
+
+        output.clear();
+        let mut underlined = SgrState::default();
+        underlined.decorate(Decoration::CurlyUnderline);
+        underlined.bg = Some(Color::Rgb(1, 2, 3));
+        writer.transition(&mut output, &underlined);
+        assert_eq!(output, "\x1b[22;4:3;39;48;2;1;2;3m");
+
+        output.clear();
+        writer.finish(&mut output);
+        assert_eq!(output, "\x1b[24;49m");
+    }
+
+    #[test]
+    fn test_minimal_resets() {
+        let bold = "bold"
+            .decoration(Decoration::Bold)
+            .color_policy(ColorPolicy::Always);
+        assert_eq!(format!("{}", bold), "\x1b[1mbold\x1b[22m");
+
+        // Leaving the bold child only turns bold off, the parent stays red
+        let nested = "red "
+            .color(Color::Red)
+            .push("bold".decoration(Decoration::Bold))
+            .push(" red")
+            .color_policy(ColorPolicy::Always);
+        let rendered = format!("{}", nested);
+        assert_eq!(rendered, "\x1b[31mred \x1b[1mbold\x1b[22m red\x1b[39m");
+        assert!(rendered.contains("22m"));
+        assert!(!rendered.contains("0m"));
+    }
+}
@@ -1,13 +1,20 @@
 #[warn(dead_code)]
-use crate::border::BorderStyle;
+use crate::border::{BorderChars, BorderStyle};
 use crate::color::Color;
 use crate::decoration::Decoration;
+use crate::gradient::Gradient;
+use crate::parser::quote;
+use std::fmt;
 
 #[derive(Clone, Debug)]
 pub struct Style {
     pub fg: Option<Color>,                   // Foreground color
     pub bg: Option<Color>,                   // Background color
+    pub fg_gradient: Option<Gradient>,       // Foreground gradient, replaces fg
+    pub bg_gradient: Option<Gradient>,       // Background gradient, replaces bg
     pub decoration: Option<Vec<Decoration>>, // Text decoration (bold, italic, etc.)
+    pub underline_style: Option<Decoration>, // Underline style, applied to any underline decoration
+    pub underline_color: Option<Color>,      // Underline color
     pub padding: Option<u8>,                 // Padding
     pub padding_top: Option<u8>,             // Padding top
     pub padding_bottom: Option<u8>,          // Padding bottom
@@ -20,6 +27,8 @@ pub struct Style {
     pub margin_right: Option<u8>,            // Margin right
     pub border_color: Option<Color>,         // Border color
     pub border_style: Option<BorderStyle>,   // Border style
+    pub border_chars: Option<BorderChars>,   // Border characters, replace border_style
+    pub link: Option<String>,                // Hyperlink URL, `{text}` is replaced by the text
 }
 
 impl Style {
@@ -28,7 +37,11 @@ impl Style {
         Style {
             fg: None,
             bg: None,
+            fg_gradient: None,
+            bg_gradient: None,
             decoration: None,
+            underline_style: None,
+            underline_color: None,
             padding: None,
             margin: None,
             margin_top: None,
@@ -37,10 +50,12 @@ impl Style {
             margin_right: None,
             border_color: None,
             border_style: None,
+            border_chars: None,
             padding_top: None,
             padding_bottom: None,
             padding_left: None,
             padding_right: None,
+            link: None,
         }
     }
 
@@ -56,6 +71,31 @@ impl Style {
         self
     }
 
+    /// Sets the underline color.
+    pub fn underline_color(mut self, color: Color) -> Self {
+        self.underline_color = Some(color);
+        self
+    }
+
+    /// Sets the underline style, which replaces the style of an underline
+    /// decoration but does not add one.
+    pub fn underline_style(mut self, style: Decoration) -> Self {
+        self.underline_style = Some(style);
+        self
+    }
+
+    /// Sets a foreground gradient.
+    pub fn fg_gradient(mut self, gradient: Gradient) -> Self {
+        self.fg_gradient = Some(gradient);
+        self
+    }
+
+    /// Sets a background gradient.
+    pub fn bg_gradient(mut self, gradient: Gradient) -> Self {
+        self.bg_gradient = Some(gradient);
+        self
+    }
+
     /// Sets the text decoration.
     pub fn decoration(mut self, deco: Vec<Decoration>) -> Self {
         self.decoration = Some(deco);
@@ -133,6 +173,197 @@ impl Style {
         self.border_style = Some(style);
         self
     }
+
+    /// Sets the border characters, which replace those of the border style.
+    pub fn border_chars(mut self, chars: BorderChars) -> Self {
+        self.border_chars = Some(chars);
+        self
+    }
+
+    /// Sets the hyperlink URL; `{text}` in it is replaced by the styled text.
+    pub fn link(mut self, url: impl Into<String>) -> Self {
+        self.link = Some(url.into());
+        self
+    }
+
+    /// Returns a copy of this style with every property set in `overrides` replaced.
+    ///
+    /// A color and a gradient for the same layer count as one property, so
+    /// an overriding color also replaces an inherited gradient. The border
+    /// style and border characters do too.
+    pub fn merge(&self, overrides: &Style) -> Style {
+        let (fg, fg_gradient) = if overrides.fg.is_some() || overrides.fg_gradient.is_some() {
+            (overrides.fg, overrides.fg_gradient.clone())
+        } else {
+            (self.fg, self.fg_gradient.clone())
+        };
+        let (bg, bg_gradient) = if overrides.bg.is_some() || overrides.bg_gradient.is_some() {
+            (overrides.bg, overrides.bg_gradient.clone())
+        } else {
+            (self.bg, self.bg_gradient.clone())
+        };
+        let (border_style, border_chars) =
+            if overrides.border_style.is_some() || overrides.border_chars.is_some() {
+                (overrides.border_style, overrides.border_chars)
+            } else {
+                (self.border_style, self.border_chars)
+            };
+        Style {
+            fg,
+            bg,
+            fg_gradient,
+            bg_gradient,
+            decoration: overrides
+                .decoration
+                .clone()
+                .or_else(|| self.decoration.clone()),
+            underline_style: overrides.underline_style.or(self.underline_style),
+            underline_color: overrides.underline_color.or(self.underline_color),
+            padding: overrides.padding.or(self.padding),
+            padding_top: overrides.padding_top.or(self.padding_top),
+            padding_bottom: overrides.padding_bottom.or(self.padding_bottom),
+            padding_left: overrides.padding_left.or(self.padding_left),
+            padding_right: overrides.padding_right.or(self.padding_right),
+            margin: overrides.margin.or(self.margin),
+            margin_top: overrides.margin_top.or(self.margin_top),
+            margin_bottom: overrides.margin_bottom.or(self.margin_bottom),
+            margin_left: overrides.margin_left.or(self.margin_left),
+            margin_right: overrides.margin_right.or(self.margin_right),
+            border_color: overrides.border_color.or(self.border_color),
+            border_style,
+            border_chars,
+            link: overrides.link.clone().or_else(|| self.link.clone()),
+        }
+    }
+}
+
+impl Style {
+    /// Returns a copy with `f` applied to every color, including gradient stops.
+    pub(crate) fn map_colors(&self, f: impl Fn(Color) -> Color) -> Style {
+        let mut style = self.clone();
+        style.fg = style.fg.map(&f);
+        style.bg = style.bg.map(&f);
+        style.border_color = style.border_color.map(&f);
+        style.underline_color = style.underline_color.map(&f);
+        for gradient in [&mut style.fg_gradient, &mut style.bg_gradient]
+            .into_iter()
+            .flatten()
+        {
+            for stop in &mut gradient.stops {
+                *stop = f(*stop);
+            }
+        }
+        style
+    }
+
+    /// Returns the style as TCSS declarations, one `property: value;` per line.
+    ///
+    /// Properties are written in a fixed order so the output is deterministic
+    /// and parses back into an equivalent style.
+    pub fn to_tcss(&self) -> String {
+        self.to_string()
+    }
+
+    /// Builds the `property: value` pairs of all set properties.
+    pub(crate) fn declarations(&self) -> Vec<String> {
+        let mut declarations = Vec::new();
+
+        // A gradient replaces the color of its layer, so only it is written
+        if let Some(gradient) = &self.fg_gradient {
+            declarations.push(format!("color: {}", gradient));
+        } else if let Some(color) = &self.fg {
+            declarations.push(format!("color: {}", color));
+        }
+        if let Some(gradient) = &self.bg_gradient {
+            declarations.push(format!("background: {}", gradient));
+        } else if let Some(color) = &self.bg {
+            declarations.push(format!("background: {}", color));
+        }
+        if let Some(decorations) = &self.decoration {
+            let names: Vec<String> = decorations.iter().map(|d| d.to_string()).collect();
+            declarations.push(format!("decoration: {}", names.join(" ")));
+        }
+        if let Some(name) = self.underline_style.and_then(|s| s.underline_style_name()) {
+            declarations.push(format!("text-decoration-style: {}", name));
+        }
+        if let Some(color) = &self.underline_color {
+            declarations.push(format!("underline-color: {}", color));
+        }
+        spacing_declarations(
+            &mut declarations,
+            "padding",
+            self.padding,
+            [
+                self.padding_top,
+                self.padding_right,
+                self.padding_bottom,
+                self.padding_left,
+            ],
+        );
+        spacing_declarations(
+            &mut declarations,
+            "margin",
+            self.margin,
+            [
+                self.margin_top,
+                self.margin_right,
+                self.margin_bottom,
+                self.margin_left,
+            ],
+        );
+        if let Some(style) = &self.border_style {
+            declarations.push(format!("border-style: {}", style));
+        }
+        if let Some(chars) = &self.border_chars {
+            declarations.push(format!("border-chars: {}", quote(&chars.to_string())));
+        }
+        if let Some(color) = &self.border_color {
+            declarations.push(format!("border-color: {}", color));
+        }
+        if let Some(link) = &self.link {
+            declarations.push(format!("link: {}", quote(link)));
+        }
+
+        declarations
+    }
+}
+
+/// Writes padding or margin using the shortest equivalent form.
+fn spacing_declarations(
+    declarations: &mut Vec<String>,
+    property: &str,
+    all: Option<u8>,
+    sides: [Option<u8>; 4],
+) {
+    if let [Some(top), Some(right), Some(bottom), Some(left)] = sides {
+        let value = if top == right && top == bottom && top == left {
+            top.to_string()
+        } else if top == bottom && right == left {
+            format!("{} {}", top, right)
+        } else {
+            format!("{} {} {} {}", top, right, bottom, left)
+        };
+        declarations.push(format!("{}: {}", property, value));
+        return;
+    }
+
+    if let Some(all) = all {
+        declarations.push(format!("{}: {}", property, all));
+    }
+    for (side, value) in ["top", "right", "bottom", "left"].iter().zip(sides) {
+        if let Some(value) = value {
+            declarations.push(format!("{}-{}: {}", property, side, value));
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for declaration in self.declarations() {
+            writeln!(f, "{};", declaration)?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for Style {
@@ -3,13 +3,16 @@
 //! This module provides functionality for styling terminal text using CSS-like syntax.
 //! It supports colors, decorations, borders, padding, and margins.
 
-use crate::border::BorderStyle;
+use crate::border::{BorderChars, BorderStyle};
+use crate::capabilities::{Capabilities, ColorDepth, ColorPolicy};
 use crate::color::Color;
 use crate::decoration::Decoration;
+use crate::gradient::{Gradient, GradientDirection};
+use crate::sgr::{SgrState, SgrWriter};
 use crate::style::Style;
 use crate::termio::Termio;
 use std::fmt;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// A trait for text that can be styled using Termio.
 ///
@@ -19,8 +22,8 @@ use unicode_width::UnicodeWidthStr;
 /// # Examples
 ///
 /// ```
-/// use terminal_css::StyledText;
-/// use terminal_css::Termio;
+/// use termio::StyledText;
+/// use termio::Termio;
 ///
 /// let tcss = Termio::from_file("examples/styles.tcss").unwrap();
 /// let text = "Hello, World!".style("header", &tcss);
@@ -66,17 +69,65 @@ pub trait StyledText {
 
 /// A string with applied TCSS styles.
 ///
-/// This struct holds the text content and its associated style.
-#[derive(Clone)]
+/// This struct holds the text content, its associated style and the
+/// capabilities of the terminal it is rendered for.
+///
+/// Nested spans follow the text and inherit its colors, decorations and
+/// link, so a bold word can sit inside a red sentence inside a bordered box.
+/// Padding, margins and borders only apply to the outermost string.
+///
+/// # Examples
+///
+/// ```
+/// use termio::prelude::*;
+///
+/// let sentence = "Build ".color(Color::Red)
+///     .push("failed".decoration(Decoration::Bold))
+///     .push(" twice")
+///     .border(BorderStyle::Rounded);
+/// assert_eq!(sentence.plain_text(), "Build failed twice");
+/// ```
+#[derive(Clone, Debug)]
 pub struct StyledString {
     text: String,
     style: Style,
+    spans: Vec<StyledString>,
+    capabilities: Capabilities,
+    color_policy: ColorPolicy,
 }
 
 impl StyledString {
     /// Creates a new styled string with the given text and style.
-    fn new(text: String, style: Style) -> Self {
-        StyledString { text, style }
+    pub(crate) fn new(text: String, style: Style) -> Self {
+        StyledString {
+            text,
+            style,
+            spans: Vec::new(),
+            capabilities: Capabilities::default(),
+            color_policy: ColorPolicy::default(),
+        }
+    }
+
+    /// Renders for a terminal with the given capabilities.
+    ///
+    /// Strings styled from a `Termio` take over its capabilities.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Renders colors downsampled to the given depth.
+    pub fn color_depth(mut self, depth: ColorDepth) -> Self {
+        self.capabilities.color_depth = depth;
+        self
+    }
+
+    /// Sets when escape codes are written.
+    ///
+    /// Strings styled from a `Termio` take over its policy.
+    pub fn color_policy(mut self, policy: ColorPolicy) -> Self {
+        self.color_policy = policy;
+        self
     }
 
     /// Gets the foreground color
@@ -94,11 +145,45 @@ impl StyledString {
         self.style.decoration.clone()
     }
 
-    /// Gets the text content
+    /// Gets the text content, without that of nested spans
     pub fn get_text(&self) -> &str {
         &self.text
     }
 
+    /// Gets the nested spans
+    pub fn spans(&self) -> &[StyledString] {
+        &self.spans
+    }
+
+    /// Gets the visible text, including that of nested spans
+    pub fn plain_text(&self) -> String {
+        let mut text = self.text.clone();
+        for span in &self.spans {
+            text.push_str(&span.plain_text());
+        }
+        text
+    }
+
+    /// Append a span inheriting this string's colors, decorations and link
+    pub fn push(mut self, span: impl Into<StyledString>) -> Self {
+        self.spans.push(span.into());
+        self
+    }
+
+    /// Appends text after the existing text and spans, in this string's style
+    pub(crate) fn append(&mut self, text: &str) {
+        if self.spans.is_empty() {
+            self.text.push_str(text);
+        } else {
+            self.spans.push(text.into());
+        }
+    }
+
+    /// Appends a span in place
+    pub(crate) fn push_span(&mut self, span: StyledString) {
+        self.spans.push(span);
+    }
+
     /// Set text color
     pub fn color(mut self, color: Color) -> Self {
         self.style.fg = Some(color);
@@ -111,6 +196,18 @@ impl StyledString {
         self
     }
 
+    /// Set a text color gradient
+    pub fn fg_gradient(mut self, gradient: Gradient) -> Self {
+        self.style.fg_gradient = Some(gradient);
+        self
+    }
+
+    /// Set a background gradient
+    pub fn bg_gradient(mut self, gradient: Gradient) -> Self {
+        self.style.bg_gradient = Some(gradient);
+        self
+    }
+
     /// Add decoration
     pub fn decoration(mut self, decoration: Decoration) -> Self {
         match &mut self.style.decoration {
@@ -120,6 +217,18 @@ impl StyledString {
         self
     }
 
+    /// Link the text to `url`, in which `{text}` is replaced by the text
+    pub fn link(mut self, url: impl Into<String>) -> Self {
+        self.style.link = Some(url.into());
+        self
+    }
+
+    /// Set underline color
+    pub fn underline_color(mut self, color: Color) -> Self {
+        self.style.underline_color = Some(color);
+        self
+    }
+
     /// Set padding (all sides)
     pub fn padding(mut self, padding: u8) -> Self {
         self.style.padding = Some(padding);
@@ -152,12 +261,32 @@ impl StyledString {
         self.style.border_color = Some(color);
         self
     }
+
+    /// Set border characters, replacing those of the border style
+    pub fn border_chars(mut self, chars: BorderChars) -> Self {
+        self.style.border_chars = Some(chars);
+        self
+    }
+}
+
+impl From<String> for StyledString {
+    fn from(text: String) -> Self {
+        StyledString::new(text, Style::new())
+    }
+}
+
+impl From<&str> for StyledString {
+    fn from(text: &str) -> Self {
+        StyledString::new(text.to_string(), Style::new())
+    }
 }
 
 impl StyledText for String {
     fn style(self, style_name: &str, tcss: &Termio) -> StyledString {
         let style = tcss.get_style(style_name).unwrap_or_default();
         StyledString::new(self, style)
+            .capabilities(tcss.capabilities().clone())
+            .color_policy(tcss.color_policy())
     }
 
     fn color(self, color: Color) -> StyledString {
@@ -216,6 +345,8 @@ impl StyledText for &str {
     fn style(self, style_name: &str, tcss: &Termio) -> StyledString {
         let style = tcss.get_style(style_name).unwrap_or_default();
         StyledString::new(self.to_string(), style)
+            .capabilities(tcss.capabilities().clone())
+            .color_policy(tcss.color_policy())
     }
 
     fn color(self, color: Color) -> StyledString {
@@ -273,25 +404,47 @@ impl StyledText for &str {
 impl fmt::Display for StyledString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut result = String::new();
-        // Split text into lines
-        let lines: Vec<&str> = self.text.lines().collect();
+        let depth = self.color_policy.color_depth(self.capabilities.color_depth);
+        // Link the text, or print the URL after it where links are unsupported
+        let hyperlinks = self.capabilities.hyperlinks && depth != ColorDepth::NoColor;
+        let mut runs = Vec::new();
+        self.collect_runs(&Style::new(), None, hyperlinks, &mut runs);
+        for run in &mut runs {
+            run.state = self.text_state(&run.style, depth);
+        }
+        // Split the runs into lines
+        let lines = split_lines(&runs);
         // Using UnicodeWidthStr for correct width calculation
-        let max_width = lines.iter().map(|line| line.width_cjk()).max().unwrap_or(0);
+        let max_width = lines.iter().map(|line| line_width(line)).max().unwrap_or(0);
         // Calculate spacing
         let (padding, margin) = self.calculate_spacing();
         // Calculate dimensions
         let (content_width, _) = self.calculate_dimensions(max_width, lines.len(), &padding);
-        // Build styles
-        let text_style = self.build_text_style();
-        let border_style = self.build_border_style();
+        // Build attributes, leaving them empty when colors are off
+        let text_state = self.text_state(&self.style, depth);
+        let border_state = self.border_state(depth);
+        let bg_state = self.background_state(depth);
         let border_chars = self.get_border_chars();
+        let painter = Painter {
+            fg: self.style.fg_gradient.as_ref(),
+            bg: self.style.bg_gradient.as_ref(),
+            depth,
+            width: content_width,
+            height: padding.top + lines.len() + padding.bottom,
+            text_left: padding.left,
+            text_top: padding.top,
+            text_width: max_width,
+            text_height: lines.len(),
+        };
         // Draw the complete element
         self.draw_element(
             &mut result,
             &lines,
-            &text_style,
-            &border_style,
-            &border_chars,
+            &text_state,
+            &border_state,
+            &bg_state,
+            border_chars.as_ref(),
+            &painter,
             padding,
             margin,
             content_width,
@@ -300,7 +453,100 @@ impl fmt::Display for StyledString {
     }
 }
 
+/// A piece of text drawn with a single style
+struct Run {
+    text: String,
+    style: Style,
+    state: SgrState,
+    link: Option<String>,
+}
+
+/// Splits runs into lines of `(text, run)` pieces the way `str::lines` would
+fn split_lines(runs: &[Run]) -> Vec<Vec<(&str, &Run)>> {
+    let mut lines = vec![Vec::new()];
+    for run in runs {
+        for (i, part) in run.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push((part, run));
+            }
+        }
+    }
+    if lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    for line in &mut lines {
+        if let Some((text, _)) = line.last_mut() {
+            *text = text.strip_suffix('\r').unwrap_or(text);
+        }
+    }
+    lines
+}
+
+/// Returns the display width of a line, counting ambiguous characters as wide
+fn line_width(line: &[(&str, &Run)]) -> usize {
+    line.iter().map(|(text, _)| text.width_cjk()).sum()
+}
+
 impl StyledString {
+    /// Flattens this string and its spans into runs, each span's style
+    /// merged over the one it inherits
+    fn collect_runs(
+        &self,
+        inherited: &Style,
+        inherited_link: Option<&str>,
+        hyperlinks: bool,
+        runs: &mut Vec<Run>,
+    ) {
+        let mut style = inherited.merge(&self.style);
+        // Decorations add up, `none` in a span turns the inherited ones off
+        if let (Some(outer), Some(inner)) = (&inherited.decoration, &self.style.decoration) {
+            style.decoration = Some(outer.iter().chain(inner).copied().collect());
+        }
+        let url = self.link_url();
+        let link = url.as_deref().or(inherited_link);
+        runs.push(Run {
+            text: self.text.clone(),
+            style: style.clone(),
+            state: SgrState::default(),
+            link: link.filter(|_| hyperlinks).map(String::from),
+        });
+        for span in &self.spans {
+            span.collect_runs(&style, link, hyperlinks, runs);
+        }
+        if let Some(url) = url.filter(|_| !hyperlinks) {
+            runs.push(Run {
+                text: format!(" ({})", url),
+                style,
+                state: SgrState::default(),
+                link: None,
+            });
+        }
+    }
+
+    /// Returns the link URL with `{text}` replaced by the percent-encoded text
+    fn link_url(&self) -> Option<String> {
+        let template = self.style.link.as_ref()?;
+        let mut encoded = String::new();
+        for byte in self.plain_text().bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        // Control characters would end the escape sequence early
+        let url: String = template
+            .replace("{text}", &encoded)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        Some(url)
+    }
+
     /// Calculates padding and margin values from the style
     fn calculate_spacing(&self) -> (Padding, Margin) {
         let padding = self.style.padding.unwrap_or(0);
@@ -334,137 +580,152 @@ impl StyledString {
         (content_width, total_height)
     }
 
-    /// Builds the text style string including colors and decorations
-    fn build_text_style(&self) -> String {
-        let mut style = String::new();
-
-        // Apply foreground color
-        if let Some(color) = &self.style.fg {
-            style.push_str(&color.to_ansi_foreground());
+    /// Returns the downsampled color of a layer without a gradient
+    fn solid(
+        &self,
+        color: Option<Color>,
+        gradient: &Option<Gradient>,
+        depth: ColorDepth,
+    ) -> Option<Color> {
+        match gradient {
+            Some(_) => None,
+            None => color.and_then(|c| c.downsample(depth)),
         }
+    }
 
-        // Apply background color
-        if let Some(color) = &self.style.bg {
-            style.push_str(&color.to_ansi_background());
+    /// Builds the attributes of text in `style` including colors and decorations
+    fn text_state(&self, style: &Style, depth: ColorDepth) -> SgrState {
+        // Downsampling drops colors when colors are off, and gradient
+        // colors are painted per cell instead
+        let mut state = SgrState {
+            fg: self.solid(style.fg, &style.fg_gradient, depth),
+            bg: self.solid(style.bg, &style.bg_gradient, depth),
+            ..SgrState::default()
+        };
+
+        // Apply decorations, which are escape codes too
+        if depth == ColorDepth::NoColor {
+            return state;
         }
-
-        // Apply decorations
-        if let Some(decorations) = &self.style.decoration {
+        if let Some(decorations) = &style.decoration {
             for decoration in decorations {
-                style.push_str(&decoration.to_ansi());
+                // Terminals without italics often render it as reverse video
+                if *decoration == Decoration::Italic && !self.capabilities.italics {
+                    continue;
+                }
+                // The underline style replaces the style of any underline
+                let decoration = match style.underline_style {
+                    Some(underline) if decoration.is_underline() => underline,
+                    _ => *decoration,
+                };
+                // Terminals without extended underlines fall back to a plain one
+                let decoration = match decoration {
+                    Decoration::CurlyUnderline
+                    | Decoration::DottedUnderline
+                    | Decoration::DashedUnderline
+                        if !self.capabilities.undercurl =>
+                    {
+                        Decoration::Underline
+                    }
+                    decoration => decoration,
+                };
+                state.decorate(decoration);
             }
         }
 
-        style
+        // Apply underline color, which is part of extended underline support
+        if self.capabilities.undercurl {
+            state.underline_color = style.underline_color.and_then(|c| c.downsample(depth));
+        }
+
+        state
     }
 
-    /// Builds the border style string
-    fn build_border_style(&self) -> String {
-        let mut style = String::new();
-        if let Some(color) = &self.style.border_color {
-            style.push_str(&color.to_ansi_foreground());
+    /// Builds the border attributes
+    fn border_state(&self, depth: ColorDepth) -> SgrState {
+        SgrState {
+            fg: self.style.border_color.and_then(|c| c.downsample(depth)),
+            ..SgrState::default()
         }
-        style
-    }
-
-    /// Gets border characters based on style
-    fn get_border_chars(&self) -> BorderChars {
-        match self.style.border_style {
-            Some(BorderStyle::Solid) => BorderChars {
-                top_left: '┌',
-                top_right: '┐',
-                bottom_left: '└',
-                bottom_right: '┘',
-                horizontal: '─',
-                vertical: '│',
-            },
-            Some(BorderStyle::Dashed) => BorderChars {
-                top_left: '┌',
-                top_right: '┐',
-                bottom_left: '└',
-                bottom_right: '┘',
-                horizontal: '┈',
-                vertical: '┊',
-            },
-            Some(BorderStyle::Rounded) => BorderChars {
-                top_left: '╭',
-                top_right: '╮',
-                bottom_left: '╰',
-                bottom_right: '╯',
-                horizontal: '─',
-                vertical: '│',
-            },
-            Some(BorderStyle::Double) => BorderChars {
-                top_left: '╔',
-                top_right: '╗',
-                bottom_left: '╚',
-                bottom_right: '╝',
-                horizontal: '═',
-                vertical: '║',
-            },
-            None => BorderChars {
-                top_left: ' ',
-                top_right: ' ',
-                bottom_left: ' ',
-                bottom_right: ' ',
-                horizontal: ' ',
-                vertical: ' ',
-            },
+    }
+
+    /// Gets border characters based on style, falling back to ASCII on
+    /// terminals without Unicode box drawing
+    fn get_border_chars(&self) -> Option<BorderChars> {
+        let chars = self
+            .style
+            .border_chars
+            .or_else(|| self.style.border_style.map(|style| style.chars()))?;
+        if self.capabilities.unicode {
+            Some(chars)
+        } else {
+            Some(chars.to_ascii())
         }
     }
 
-    /// Builds the background style string
-    fn build_background_style(&self) -> String {
-        let mut style = String::new();
-        if let Some(color) = &self.style.bg {
-            style.push_str(&color.to_ansi_background());
+    /// Builds the attributes of the padding, which only has a background
+    fn background_state(&self, depth: ColorDepth) -> SgrState {
+        SgrState {
+            bg: self.solid(self.style.bg, &self.style.bg_gradient, depth),
+            ..SgrState::default()
         }
-        style
     }
 
     /// Draws the complete element with all its components
+    ///
+    /// Attributes are switched with the fewest codes needed and turned off
+    /// again at the end of each line, without a full reset.
+    #[allow(clippy::too_many_arguments)]
     fn draw_element(
         &self,
         output: &mut String,
-        lines: &[&str],
-        text_style: &str,
-        border_style: &str,
-        border_chars: &BorderChars,
+        lines: &[Vec<(&str, &Run)>],
+        text_state: &SgrState,
+        border_state: &SgrState,
+        bg_state: &SgrState,
+        border_chars: Option<&BorderChars>,
+        painter: &Painter,
         padding: Padding,
         margin: Margin,
         content_width: usize,
     ) {
+        let mut sgr = SgrWriter::default();
+
         // Add top margin
         for _ in 0..margin.top {
             output.push('\n');
         }
 
         let margin_left = " ".repeat(margin.left);
-        let bg_style = self.build_background_style();
 
         // Draw top border if border style is set
-        if self.style.border_style.is_some() {
+        if let Some(border_chars) = border_chars {
             output.push_str(&margin_left);
-            output.push_str(border_style);
+            sgr.transition(output, border_state);
             output.push(border_chars.top_left);
-            output.push_str(&border_chars.horizontal.to_string().repeat(content_width));
+            output.push_str(&border_chars.top.to_string().repeat(content_width));
             output.push(border_chars.top_right);
-            output.push_str(&Decoration::reset());
+            sgr.finish(output);
             output.push('\n');
         }
 
         // Draw top padding
-        for _ in 0..padding.top {
-            if self.style.border_style.is_some() {
+        for row in 0..padding.top {
+            if let Some(border_chars) = border_chars {
                 output.push_str(&margin_left);
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&bg_style);
-                output.push_str(&" ".repeat(content_width));
-                output.push_str(&Decoration::reset());
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&Decoration::reset());
+                sgr.transition(output, border_state);
+                output.push(border_chars.left);
+                painter.paint(
+                    &mut sgr,
+                    output,
+                    &" ".repeat(content_width),
+                    0,
+                    row,
+                    bg_state,
+                );
+                sgr.transition(output, border_state);
+                output.push(border_chars.right);
+                sgr.finish(output);
             }
             output.push('\n');
         }
@@ -472,63 +733,107 @@ impl StyledString {
         // Draw text lines with padding
         lines.iter().enumerate().for_each(|(i, line)| {
             output.push_str(&margin_left);
-            if self.style.border_style.is_some() {
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&Decoration::reset());
+            if let Some(border_chars) = border_chars {
+                sgr.transition(output, border_state);
+                output.push(border_chars.left);
             }
-            output.push_str(&bg_style);
+            let row = padding.top + i;
             // Left padding
-            output.push_str(&" ".repeat(padding.left));
-            // Text content with style
-            output.push_str(text_style);
-            output.push_str(line);
+            let column = painter.paint(
+                &mut sgr,
+                output,
+                &" ".repeat(padding.left),
+                0,
+                row,
+                bg_state,
+            );
+            // Text content with the style of each run, opening links after
+            // their attributes
+            let mut column = column;
+            let mut open_link = None;
+            for (text, run) in line {
+                sgr.transition(output, &run.state);
+                if run.link != open_link {
+                    if open_link.is_some() {
+                        output.push_str("\x1b]8;;\x1b\\");
+                    }
+                    if let Some(url) = &run.link {
+                        output.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+                    }
+                    open_link = run.link.clone();
+                }
+                let painter = Painter {
+                    fg: run.style.fg_gradient.as_ref(),
+                    bg: run.style.bg_gradient.as_ref(),
+                    ..*painter
+                };
+                column = painter.paint(&mut sgr, output, text, column, row, &run.state);
+            }
+            if open_link.is_some() {
+                output.push_str("\x1b]8;;\x1b\\");
+            }
             // Using UnicodeWidthStr for width calculation
-            let line_width = line.width();
-            let extra_width = line.width_cjk() - line.width();
-            let padding_after_text = content_width - line_width - padding.left - padding.right + extra_width;
-            
-            output.push_str(&" ".repeat(padding_after_text));
-            output.push_str(&Decoration::reset());
+            let padding_after_text =
+                content_width - padding.left - padding.right - line_width(line);
+
+            let column = painter.paint(
+                &mut sgr,
+                output,
+                &" ".repeat(padding_after_text),
+                column,
+                row,
+                text_state,
+            );
             // Right padding
-            output.push_str(&bg_style);
-            output.push_str(&" ".repeat(padding.right));
-            output.push_str(&Decoration::reset());
-            if self.style.border_style.is_some() {
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&Decoration::reset());
+            painter.paint(
+                &mut sgr,
+                output,
+                &" ".repeat(padding.right),
+                column,
+                row,
+                bg_state,
+            );
+            if let Some(border_chars) = border_chars {
+                sgr.transition(output, border_state);
+                output.push(border_chars.right);
             }
+            sgr.finish(output);
             // Add a new line if it's not the last line and border style is set
-            if i < lines.len() && self.style.border_style.is_some() {
+            if i < lines.len() && border_chars.is_some() {
                 output.push('\n');
             }
         });
 
         // Draw bottom padding
-        for _ in 0..padding.bottom {
-            if self.style.border_style.is_some() {
+        for i in 0..padding.bottom {
+            if let Some(border_chars) = border_chars {
                 output.push_str(&margin_left);
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&bg_style);
-                output.push_str(&" ".repeat(content_width));
-                output.push_str(&Decoration::reset());
-                output.push_str(border_style);
-                output.push(border_chars.vertical);
-                output.push_str(&Decoration::reset());
+                sgr.transition(output, border_state);
+                output.push(border_chars.left);
+                let row = padding.top + lines.len() + i;
+                painter.paint(
+                    &mut sgr,
+                    output,
+                    &" ".repeat(content_width),
+                    0,
+                    row,
+                    bg_state,
+                );
+                sgr.transition(output, border_state);
+                output.push(border_chars.right);
+                sgr.finish(output);
             }
             output.push('\n');
         }
 
         // Draw bottom border if border style is set
-        if self.style.border_style.is_some() {
+        if let Some(border_chars) = border_chars {
             output.push_str(&margin_left);
-            output.push_str(border_style);
+            sgr.transition(output, border_state);
             output.push(border_chars.bottom_left);
-            output.push_str(&border_chars.horizontal.to_string().repeat(content_width));
+            output.push_str(&border_chars.bottom.to_string().repeat(content_width));
             output.push(border_chars.bottom_right);
-            output.push_str(&Decoration::reset());
+            sgr.finish(output);
         }
 
         // Add bottom margin
@@ -538,6 +843,98 @@ impl StyledString {
     }
 }
 
+/// Writes text cell by cell with the colors of the style's gradients.
+///
+/// Positions are relative to the content box inside the border; the
+/// foreground gradient spans the text only, the background gradient
+/// the whole box including padding. Columns count ambiguous characters
+/// as wide, like [`line_width`].
+#[derive(Clone, Copy)]
+struct Painter<'a> {
+    fg: Option<&'a Gradient>,
+    bg: Option<&'a Gradient>,
+    depth: ColorDepth,
+    width: usize,
+    height: usize,
+    text_left: usize,
+    text_top: usize,
+    text_width: usize,
+    text_height: usize,
+}
+
+impl Painter<'_> {
+    /// Whether any gradient produces escape codes
+    fn is_active(&self) -> bool {
+        self.depth != ColorDepth::NoColor && (self.fg.is_some() || self.bg.is_some())
+    }
+
+    /// Writes `text` with the attributes of `base`, starting at `column` of
+    /// `row`, and returns the column after it
+    fn paint(
+        &self,
+        sgr: &mut SgrWriter,
+        output: &mut String,
+        text: &str,
+        column: usize,
+        row: usize,
+        base: &SgrState,
+    ) -> usize {
+        if text.is_empty() {
+            return column;
+        }
+        if !self.is_active() {
+            sgr.transition(output, base);
+            output.push_str(text);
+            return column + text.width_cjk();
+        }
+
+        let mut column = column;
+        for c in text.chars() {
+            let mut state = base.clone();
+            if let Some(color) = self.fg.and_then(|g| {
+                let x = column.saturating_sub(self.text_left);
+                let y = row.saturating_sub(self.text_top);
+                self.color_at(g, x, y, self.text_width, self.text_height)
+            }) {
+                state.fg = Some(color);
+            }
+            if let Some(color) = self
+                .bg
+                .and_then(|g| self.color_at(g, column, row, self.width, self.height))
+            {
+                state.bg = Some(color);
+            }
+            // Neighbouring cells often share a color once downsampled, which
+            // the writer skips
+            sgr.transition(output, &state);
+            output.push(c);
+            column += c.width_cjk().unwrap_or(0);
+        }
+        column
+    }
+
+    /// Returns the downsampled gradient color at `x`, `y` of a `width` by `height` area
+    fn color_at(
+        &self,
+        gradient: &Gradient,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Option<Color> {
+        let (position, size) = match gradient.direction {
+            GradientDirection::Horizontal => (x, width),
+            GradientDirection::Vertical => (y, height),
+        };
+        let t = if size > 1 {
+            position as f64 / (size - 1) as f64
+        } else {
+            0.0
+        };
+        gradient.at(t)?.downsample(self.depth)
+    }
+}
+
 /// Represents padding values for all sides
 #[derive(Debug, Clone)]
 struct Padding {
@@ -557,13 +954,79 @@ struct Margin {
     right: usize,
 }
 
-/// Represents border characters for all sides
-#[derive(Debug, Clone)]
-struct BorderChars {
-    top_left: char,
-    top_right: char,
-    bottom_left: char,
-    bottom_right: char,
-    horizontal: char,
-    vertical: char,
+#[cfg(test)]
+mod tests {
+    use super::{StyledString, StyledText};
+    use crate::border::BorderStyle;
+    use crate::capabilities::{Capabilities, ColorPolicy};
+    use crate::color::Color;
+    use crate::decoration::Decoration;
+    use crate::gradient::Gradient;
+
+    #[test]
+    fn test_gradient_ambiguous_width() {
+        // The arrow is ambiguous and takes two of the three columns
+        let gradient = Gradient::horizontal(vec![Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255)]);
+        let text = StyledString::from("\u{2192}x")
+            .fg_gradient(gradient)
+            .color_policy(ColorPolicy::Always);
+        assert!(text
+            .to_string()
+            .contains("\x1b[38;2;255;0;0m\u{2192}\x1b[38;2;0;0;255mx"));
+    }
+
+    #[test]
+    fn test_color_policy_never() {
+        let parser = tcss! {
+            "boxed" => {
+                fg: Color::Green,
+                decoration: vec![Decoration::Bold],
+                border_style: BorderStyle::Solid,
+                padding: 1
+            }
+        }
+        .with_color_policy(ColorPolicy::Never);
+
+        let plain = format!("{}", "Plain".style("boxed", &parser));
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.contains("│ Plain │"));
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        let ticket = "PROJ 42"
+            .color(Color::Blue)
+            .link("https://tracker.example/{text}")
+            .color_policy(ColorPolicy::Always);
+
+        let linked = format!("{}", ticket.clone());
+        assert!(
+            linked.contains("\x1b]8;;https://tracker.example/PROJ%2042\x1b\\PROJ 42\x1b]8;;\x1b\\")
+        );
+        let fallback = format!(
+            "{}",
+            ticket.capabilities(Capabilities::default().hyperlinks(false))
+        );
+        assert!(fallback.contains("PROJ 42 (https://tracker.example/PROJ%2042)"));
+        assert!(!fallback.contains("\x1b]8"));
+    }
+
+    #[test]
+    fn test_nested_spans() {
+        let sentence = "a "
+            .color(Color::Red)
+            .decoration(Decoration::Italic)
+            .push("bold".decoration(Decoration::Bold))
+            .push(" z")
+            .border(BorderStyle::Solid)
+            .color_policy(ColorPolicy::Always);
+
+        let rendered = format!("{}", sentence);
+        assert!(rendered.contains("\x1b[3;31ma \x1b[1mbold\x1b[22m z\x1b[23;39m│"));
+        // Widths come from the visible text only
+        assert!(rendered.starts_with("┌────────┐"));
+
+        let plain = format!("{}", sentence.color_policy(ColorPolicy::Never));
+        assert_eq!(plain, "┌────────┐\n│a bold z│\n└────────┘");
+    }
 }
@@ -1,382 +1,975 @@
-use crate::border::BorderStyle;
+use crate::capabilities::{Capabilities, ColorPolicy};
 use crate::color::Color;
-use crate::decoration::Decoration;
+use crate::condition::Condition;
+use crate::contrast::{ContrastIssue, ContrastLevel};
+use crate::diagnostic::ParseError;
+use crate::import::ImportResolver;
+use crate::palette::Palette;
+use crate::parser::{quote, Parser};
 use crate::style::Style;
-use std::collections::HashMap;
+use crate::vision::{self, ColorBlindness, ColorConflict};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Termio {
-    styles: HashMap<String, Style>,
+    pub(crate) styles: HashMap<String, Style>,
+    /// Parent names from `extends` clauses, by theme and element name
+    pub(crate) parents: HashMap<(Option<String>, String), Vec<String>>,
+    /// Variables defined in sheets with `@var name` or `--name`
+    pub(crate) variables: HashMap<String, String>,
+    /// Variables set from Rust; these take precedence over sheet definitions
+    pub(crate) variable_overrides: HashMap<String, String>,
+    /// Loads `@import`ed sheets; files relative to the importer when unset
+    pub(crate) resolver: Option<Arc<dyn ImportResolver>>,
+    /// Elements defined inside `@theme` blocks, by theme name
+    pub(crate) themes: HashMap<String, HashMap<String, Style>>,
+    /// The theme `get_style` resolves elements for
+    pub(crate) theme: Option<String>,
+    /// Styles from `@when` blocks, in source order
+    pub(crate) conditional: Vec<ConditionalStyle>,
+    /// The terminal `@when` conditions are evaluated against
+    pub(crate) capabilities: Capabilities,
+    pub(crate) color_policy: ColorPolicy,
+    /// Colors defined in sheets with `@palette`
+    pub(crate) palette: Palette,
+    /// Palette entries set from Rust; these take precedence over sheet definitions
+    pub(crate) palette_overrides: Palette,
+    /// Whether `get_style` replaces colors with their palette entries
+    pub(crate) remap_palette: bool,
+    /// Level `get_style` adjusts foregrounds to, if any
+    pub(crate) readable_level: Option<ContrastLevel>,
+    /// Color blindness `get_style` simulates, if any
+    pub(crate) simulation: Option<ColorBlindness>,
+    /// Files and imports the styles were parsed from
+    pub(crate) sources: Vec<PathBuf>,
 }
 
-/// Custom error type for TCSS parsing errors
-#[derive(Debug)]
-pub enum ParseError {
-    InvalidSyntax(String),
-    DuplicateElement(String),
+/// How `Termio::merge` handles an element defined in both sheets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Fail without changing anything, like parsing a duplicate `@element`
+    Error,
+    /// The element from the merged sheet replaces the existing one
+    Replace,
+    /// Properties set in the merged sheet override the existing ones,
+    /// all other properties are kept
+    Cascade,
 }
 
-impl fmt::Display for ParseError {
+/// Error returned by `Termio::merge` with `MergePolicy::Error`.
+#[derive(Debug, PartialEq)]
+pub enum MergeError {
+    /// An element defined in both sheets, with its theme if it is themed
+    DuplicateElement { name: String, theme: Option<String> },
+}
+
+impl fmt::Display for MergeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ParseError::InvalidSyntax(msg) => write!(f, "Invalid syntax: {}", msg),
-            ParseError::DuplicateElement(name) => write!(f, "Duplicate element name: {}", name),
+            MergeError::DuplicateElement { name, theme: None } => {
+                write!(f, "Duplicate element name: {}", name)
+            }
+            MergeError::DuplicateElement {
+                name,
+                theme: Some(theme),
+            } => write!(f, "Duplicate element name: {} in theme {}", name, theme),
         }
     }
 }
 
-impl Error for ParseError {}
+impl Error for MergeError {}
 
-impl From<String> for ParseError {
-    fn from(s: String) -> Self {
-        ParseError::InvalidSyntax(s)
-    }
+/// An element defined inside a `@when` block.
+///
+/// Its properties are applied on top of the base style of the same name
+/// when the condition matches the current capabilities.
+#[derive(Clone, Debug)]
+pub(crate) struct ConditionalStyle {
+    pub(crate) condition: Condition,
+    /// Only applies while this theme is active, if set
+    pub(crate) theme: Option<String>,
+    pub(crate) name: String,
+    pub(crate) style: Style,
 }
 
 impl Termio {
     /// Creates a new Termio with an empty style map.
+    ///
+    /// **The terminal is not detected.** The capabilities default to a
+    /// truecolor terminal with every feature (Unicode, hyperlinks, italics,
+    /// extended underlines) and an unknown size, so `@when` blocks and
+    /// rendering assume all of them. Call
+    /// `.with_capabilities(Capabilities::detect())` to use the actual
+    /// terminal's. Only escape codes themselves follow the environment,
+    /// through `ColorPolicy::Auto`.
     pub fn new() -> Self {
         Termio {
             styles: HashMap::new(),
+            parents: HashMap::new(),
+            variables: HashMap::new(),
+            variable_overrides: HashMap::new(),
+            resolver: None,
+            themes: HashMap::new(),
+            theme: None,
+            conditional: Vec::new(),
+            capabilities: Capabilities::default(),
+            color_policy: ColorPolicy::default(),
+            palette: Palette::new(),
+            palette_overrides: Palette::new(),
+            remap_palette: false,
+            readable_level: None,
+            simulation: None,
+            sources: Vec::new(),
         }
     }
 
-    pub fn from_file(path: &str) -> Result<Self, ParseError> {
+    /// Returns an empty Termio with the same settings: resolver, active
+    /// theme, capabilities, color policy, palette remapping, contrast
+    /// adjustment, simulation, and variables and palette entries set from Rust.
+    pub(crate) fn with_same_settings(&self) -> Termio {
+        let mut termio = Termio::new();
+        termio.copy_settings(self);
+        termio
+    }
+
+    /// Replaces the settings of this Termio with those of `other`, keeping
+    /// the parsed styles.
+    pub(crate) fn copy_settings(&mut self, other: &Termio) {
+        self.variable_overrides = other.variable_overrides.clone();
+        self.resolver = other.resolver.clone();
+        self.theme = other.theme.clone();
+        self.capabilities = other.capabilities.clone();
+        self.color_policy = other.color_policy;
+        self.palette_overrides = other.palette_overrides.clone();
+        self.remap_palette = other.remap_palette;
+        self.readable_level = other.readable_level;
+        self.simulation = other.simulation;
+    }
+
+    /// Sets the terminal capabilities `@when` blocks are evaluated against.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Replaces the terminal capabilities, e.g. after the terminal was resized.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Returns the terminal capabilities `@when` blocks are evaluated against.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Sets when styled strings write escape codes.
+    ///
+    /// The default, `ColorPolicy::Auto`, leaves them out when stdout is not
+    /// a terminal or the environment asks for no colors; use
+    /// [`ColorPolicy::resolve`] for output written to stderr. `@when` blocks
+    /// are still evaluated against the capabilities.
+    pub fn with_color_policy(mut self, policy: ColorPolicy) -> Self {
+        self.color_policy = policy;
+        self
+    }
+
+    /// Replaces the color policy.
+    pub fn set_color_policy(&mut self, policy: ColorPolicy) {
+        self.color_policy = policy;
+    }
+
+    /// Returns when styled strings write escape codes.
+    pub fn color_policy(&self) -> ColorPolicy {
+        self.color_policy
+    }
+
+    /// Adds palette entries, taking precedence over `@palette` blocks.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette_overrides.extend(&palette);
+        self
+    }
+
+    /// Returns the palette from `@palette` blocks and `with_palette`.
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.palette.clone();
+        palette.extend(&self.palette_overrides);
+        palette
+    }
+
+    /// Makes `get_style` replace basic colors and color codes that have a
+    /// palette entry with its RGB value, so they render the same on every
+    /// truecolor terminal regardless of its color scheme.
+    pub fn with_palette_remapping(mut self, enabled: bool) -> Self {
+        self.remap_palette = enabled;
+        self
+    }
+
+    /// Makes `get_style` adjust foregrounds that do not meet `level` on
+    /// their background to the closest readable shade.
+    pub fn with_readable_colors(mut self, level: ContrastLevel) -> Self {
+        self.readable_level = Some(level);
+        self
+    }
+
+    /// Reports every element whose foreground does not meet `level` on its
+    /// background, sorted by name.
+    ///
+    /// Elements are checked as `get_style` returns them, so for the active
+    /// theme, capabilities and palette remapping, but without the adjustment
+    /// of `with_readable_colors`.
+    pub fn lint_contrast(&self, level: ContrastLevel) -> Vec<ContrastIssue> {
+        self.lint_styles()
+            .into_iter()
+            .filter_map(|(name, style)| {
+                let mut issue = style.check_contrast(level).err()?;
+                issue.element = Some(name.clone());
+                Some(issue)
+            })
+            .collect()
+    }
+
+    /// Renders every color as seen with the given color blindness, to
+    /// preview the sheet; `None` turns the simulation off.
+    pub fn with_simulation(mut self, kind: Option<ColorBlindness>) -> Self {
+        self.simulation = kind;
+        self
+    }
+
+    /// Reports the colors that become hard to tell apart with the given
+    /// color blindness: pairs of an element's colors (`color`, `background`
+    /// and `border-color`), then pairs of elements by their `color`, such as
+    /// `success` and `error`. Elements are checked like in `lint_contrast`.
+    pub fn lint_color_blindness(&self, kind: ColorBlindness) -> Vec<ColorConflict> {
+        let styles = self.lint_styles();
+        let mut conflicts: Vec<ColorConflict> = styles
+            .iter()
+            .flat_map(|(name, style)| {
+                let colors: Vec<(&'static str, Color)> = [
+                    ("color", style.fg),
+                    ("background", style.bg),
+                    ("border-color", style.border_color),
+                ]
+                .into_iter()
+                .filter_map(|(property, color)| Some((property, color?)))
+                .collect();
+                vision::conflicts(name, kind, &colors)
+            })
+            .collect();
+        let foregrounds: Vec<(&str, Color)> = styles
+            .iter()
+            .filter_map(|(name, style)| Some((name.as_str(), style.fg?)))
+            .collect();
+        conflicts.extend(vision::element_conflicts(kind, &foregrounds));
+        conflicts
+    }
+
+    /// Returns every element as `get_style` resolves it, before contrast
+    /// adjustment and simulation, sorted by name.
+    fn lint_styles(&self) -> Vec<(&String, Style)> {
+        let mut names: Vec<&String> = self.styles.keys().collect();
+        if let Some(themed) = self.theme.as_ref().and_then(|t| self.themes.get(t)) {
+            names.extend(themed.keys());
+        }
+        // Elements only defined in `@when` blocks exist while they match
+        names.extend(
+            self.conditional
+                .iter()
+                .filter(|c| c.theme.is_none() || c.theme == self.theme)
+                .map(|c| &c.name),
+        );
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let mut style = self.resolve_style(name)?;
+                if self.remap_palette {
+                    style = self.palette().apply(&style);
+                }
+                Some((name, style))
+            })
+            .collect()
+    }
+
+    /// Sets the resolver used to load `@import`ed sheets.
+    pub fn with_resolver(mut self, resolver: impl ImportResolver + 'static) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Reads and parses a TCSS file; diagnostics carry the file path.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ParseError> {
         let mut tcss = Self::new();
-        let content = std::fs::read_to_string(path).map_err(|e| ParseError::InvalidSyntax(e.to_string()))?;
-        tcss.parse(&content)?;
+        tcss.parse_file(path)?;
         Ok(tcss)
     }
 
+    /// Reads and parses a TCSS file, collecting every error instead of
+    /// stopping at the first one. Returns the partially built Termio.
+    pub fn from_file_recovering(path: impl AsRef<Path>) -> (Self, Vec<ParseError>) {
+        let mut tcss = Self::new();
+        let errors = tcss.parse_file_recovering(path);
+        (tcss, errors)
+    }
+
+    /// Reads and parses a TCSS file into this Termio.
+    ///
+    /// `@import`s in the file are resolved relative to it.
+    pub fn parse_file(&mut self, path: impl AsRef<Path>) -> Result<(), ParseError> {
+        let path = path.as_ref();
+        self.add_source(path);
+        let content = Self::read(path)?;
+        Parser::new(&content)
+            .with_path(path)
+            .parse(self)
+            .map_err(|e| e.with_path(path))
+    }
+
+    /// Reads and parses a TCSS file into this Termio, collecting every error.
+    pub fn parse_file_recovering(&mut self, path: impl AsRef<Path>) -> Vec<ParseError> {
+        let path = path.as_ref();
+        self.add_source(path);
+        let content = match Self::read(path) {
+            Ok(content) => content,
+            Err(e) => return vec![e],
+        };
+        let mut parser = Parser::new(&content).with_path(path).recovering();
+        // A recovering parser records errors instead of returning them
+        let _ = parser.parse(self);
+        parser
+            .into_errors()
+            .into_iter()
+            .map(|e| e.with_path(path))
+            .collect()
+    }
+
+    /// Returns the files (including imports) the styles were parsed from.
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    pub(crate) fn add_source(&mut self, path: &Path) {
+        if !self.sources.iter().any(|p| p == path) {
+            self.sources.push(path.to_path_buf());
+        }
+    }
+
+    fn read(path: &Path) -> Result<String, ParseError> {
+        std::fs::read_to_string(path)
+            .map_err(|e| ParseError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Selects the `@theme` that `get_style` resolves elements for.
+    pub fn with_theme(mut self, theme: &str) -> Self {
+        self.set_theme(theme);
+        self
+    }
+
+    /// Switches the active `@theme`.
+    ///
+    /// Elements the theme doesn't define fall back to their un-themed
+    /// definition, so an unknown theme name behaves like no theme at all.
+    pub fn set_theme(&mut self, theme: &str) {
+        self.theme = Some(theme.to_string());
+    }
+
+    /// Switches back to the un-themed definitions.
+    pub fn clear_theme(&mut self) {
+        self.theme = None;
+    }
+
+    /// Returns the active theme, if any.
+    pub fn theme(&self) -> Option<&str> {
+        self.theme.as_deref()
+    }
+
+    /// Returns the names of all themes defined in parsed sheets, including
+    /// those holding only `@when` blocks, sorted.
+    pub fn themes(&self) -> Vec<&str> {
+        let mut themes: Vec<&str> = self.themes.keys().map(String::as_str).collect();
+        themes.extend(self.conditional.iter().filter_map(|c| c.theme.as_deref()));
+        themes.sort();
+        themes.dedup();
+        themes
+    }
+
     /// Retrieves a style by name, returning None if not found.
+    ///
+    /// The definition from the active theme is used if it has one, the
+    /// un-themed definition otherwise. Matching `@when` variants are then
+    /// merged on top, in source order.
+    ///
+    /// With `with_palette_remapping`, colors are then replaced with their
+    /// palette entries, with `with_readable_colors` the foreground is
+    /// adjusted, and with `with_simulation` every color is simulated last.
     pub fn get_style(&self, name: &str) -> Option<Style> {
-        self.styles.get(name).cloned()
+        let mut style = self.resolve_style(name)?;
+        if self.remap_palette {
+            style = self.palette().apply(&style);
+        }
+        if let Some(level) = self.readable_level {
+            style = style.with_readable_fg(level);
+        }
+        if let Some(kind) = self.simulation {
+            style = style.map_colors(|c| c.simulate(kind));
+        }
+        Some(style)
     }
 
-    /// Parses TCSS content and populates the style map.
-    pub fn parse(&mut self, content: &str) -> Result<(), ParseError> {
-        let mut lines = content.lines().peekable();
-        let mut current_style = None;
-        let mut current_name = None;
-
-        while let Some(line) = lines.next() {
-            let line = line.trim();
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with("//") {
+    /// Resolves an element for the active theme and capabilities.
+    fn resolve_style(&self, name: &str) -> Option<Style> {
+        let capabilities = self.effective_capabilities();
+        let theme = self.theme.as_deref();
+        let base = theme
+            .and_then(|theme| self.inherited(Some(theme), name))
+            .or_else(|| self.inherited(None, name));
+
+        self.conditional
+            .iter()
+            .filter(|c| c.name == name && (c.theme.is_none() || c.theme.as_deref() == theme))
+            .filter(|c| c.condition.matches(&capabilities))
+            .fold(base, |style, c| {
+                Some(style.unwrap_or_default().merge(&c.style))
+            })
+    }
+
+    /// Returns the capabilities `@when` conditions are evaluated against,
+    /// with colors off whenever the color policy turns them off.
+    fn effective_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities.clone();
+        capabilities.color_depth = self.color_policy.color_depth(capabilities.color_depth);
+        capabilities
+    }
+
+    /// Looks up an element merged over the parents of its `extends` clause.
+    ///
+    /// Parents are resolved on every lookup, so replacing or merging into a
+    /// parent reaches its children. Later parents override earlier ones and
+    /// the element's own properties override all of them.
+    pub(crate) fn inherited(&self, theme: Option<&str>, name: &str) -> Option<Style> {
+        self.inherited_from(theme, name, &mut Vec::new())
+    }
+
+    fn inherited_from<'a>(
+        &'a self,
+        theme: Option<&'a str>,
+        name: &'a str,
+        stack: &mut Vec<(Option<&'a str>, &'a str)>,
+    ) -> Option<Style> {
+        let own = self.element(theme, name)?;
+        let Some(parents) = self.parents_of(theme, name) else {
+            return Some(own.clone());
+        };
+        stack.push((theme, name));
+        let mut merged = Style::new();
+        for parent in parents {
+            let key = (self.parent_theme(theme, name, parent), parent.as_str());
+            // Cycles were reported when parsing
+            if stack.contains(&key) {
                 continue;
             }
+            if let Some(style) = self.inherited_from(key.0, key.1, stack) {
+                merged = merged.merge(&style);
+            }
+        }
+        stack.pop();
+        Some(merged.merge(own))
+    }
 
-            if line.starts_with("@element") {
-                if let Some(name) = current_name {
-                    if self.styles.contains_key(&name) {
-                        return Err(ParseError::DuplicateElement(name));
-                    }
-                    self.styles.insert(name, current_style.unwrap_or_default());
-                }
+    /// Returns the parents from the `extends` clause of an element, if any.
+    pub(crate) fn parents_of(&self, theme: Option<&str>, name: &str) -> Option<&Vec<String>> {
+        self.parents
+            .get(&(theme.map(String::from), name.to_string()))
+    }
 
-                let name = line
-                    .split('"')
-                    .nth(1)
-                    .ok_or_else(|| ParseError::InvalidSyntax("Missing element name".to_string()))?;
-                current_name = Some(name.to_string());
-                current_style = Some(Style::new());
-            } else if let Some(style) = &mut current_style {
-                if line == "}" {
-                    if let Some(name) = current_name.take() {
-                        if self.styles.contains_key(&name) {
-                            return Err(ParseError::DuplicateElement(name));
-                        }
-                        self.styles
-                            .insert(name, current_style.take().unwrap_or_default());
-                    }
-                } else {
-                    let parts: Vec<&str> = line.split(':').collect();
-                    if parts.len() != 2 {
-                        return Err(ParseError::InvalidSyntax(format!(
-                            "Invalid property: {}",
-                            line
-                        )));
-                    }
-
-                    let property = parts[0].trim();
-                    let value = parts[1].trim().trim_end_matches(';');
-
-                    match property {
-                        "color" => {
-                            style.fg = Some(
-                                Color::from_str(value)
-                                    .map_err(|e| ParseError::InvalidSyntax(e.to_string()))?,
-                            )
-                        }
-                        "background" => {
-                            style.bg = Some(
-                                Color::from_str(value)
-                                    .map_err(|e| ParseError::InvalidSyntax(e.to_string()))?,
-                            )
-                        }
-                        "decoration" => style.decoration = Some(self.parse_decoration(value)?),
-                        "padding" => {
-                            let values: Vec<&str> = value.split_whitespace().collect();
-                            match values.len() {
-                                1 => {
-                                    let pad = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.padding = Some(pad);
-                                    style.padding_top = Some(pad);
-                                    style.padding_bottom = Some(pad);
-                                    style.padding_left = Some(pad);
-                                    style.padding_right = Some(pad);
-                                }
-                                2 => {
-                                    let v = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let h = values[1].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.padding_top = Some(v);
-                                    style.padding_bottom = Some(v);
-                                    style.padding_left = Some(h);
-                                    style.padding_right = Some(h);
-                                }
-                                4 => {
-                                    let top = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let right = values[1].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let bottom = values[2].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let left = values[3].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid padding value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.padding_top = Some(top);
-                                    style.padding_right = Some(right);
-                                    style.padding_bottom = Some(bottom);
-                                    style.padding_left = Some(left);
-                                }
-                                _ => {
-                                    return Err(ParseError::InvalidSyntax(
-                                        "Invalid padding format. Use 1, 2, or 4 values".to_string(),
-                                    ))
-                                }
-                            }
-                        }
-                        "padding-top" => {
-                            style.padding_top = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid padding-top value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "padding-bottom" => {
-                            style.padding_bottom = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid padding-bottom value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "padding-left" => {
-                            style.padding_left = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid padding-left value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "padding-right" => {
-                            style.padding_right = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid padding-right value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "margin" => {
-                            let values: Vec<&str> = value.split_whitespace().collect();
-                            match values.len() {
-                                1 => {
-                                    let margin = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.margin = Some(margin);
-                                    style.margin_top = Some(margin);
-                                    style.margin_bottom = Some(margin);
-                                    style.margin_left = Some(margin);
-                                    style.margin_right = Some(margin);
-                                }
-                                2 => {
-                                    let v = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let h = values[1].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.margin_top = Some(v);
-                                    style.margin_bottom = Some(v);
-                                    style.margin_left = Some(h);
-                                    style.margin_right = Some(h);
-                                }
-                                4 => {
-                                    let top = values[0].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let right = values[1].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let bottom = values[2].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    let left = values[3].parse().map_err(|_| {
-                                        ParseError::InvalidSyntax(format!(
-                                            "Invalid margin value: {}",
-                                            value
-                                        ))
-                                    })?;
-                                    style.margin_top = Some(top);
-                                    style.margin_right = Some(right);
-                                    style.margin_bottom = Some(bottom);
-                                    style.margin_left = Some(left);
-                                }
-                                _ => {
-                                    return Err(ParseError::InvalidSyntax(
-                                        "Invalid margin format. Use 1, 2, or 4 values".to_string(),
-                                    ))
-                                }
-                            }
-                        }
-                        "margin-top" => {
-                            style.margin_top = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid margin-top value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "margin-bottom" => {
-                            style.margin_bottom = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid margin-bottom value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "margin-left" => {
-                            style.margin_left = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid margin-left value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "margin-right" => {
-                            style.margin_right = Some(value.parse().map_err(|_| {
-                                ParseError::InvalidSyntax(format!(
-                                    "Invalid margin-right value: {}",
-                                    value
-                                ))
-                            })?)
-                        }
-                        "border-color" => {
-                            style.border_color = Some(
-                                Color::from_str(value)
-                                    .map_err(|e| ParseError::InvalidSyntax(e.to_string()))?,
-                            )
-                        }
-                        "border-style" => {
-                            style.border_style = Some(self.parse_border_style(value)?)
-                        }
-                        "border" => {
-                            let (s, c) = value.split_once(" ").unwrap();
-                            style.border_style = Some(self.parse_border_style(s)?);
-                            style.border_color = Some(
-                                Color::from_str(c)
-                                    .map_err(|e| ParseError::InvalidSyntax(e.to_string()))?,
-                            );
-                        }
-                        _ => {
-                            return Err(ParseError::InvalidSyntax(format!(
-                                "Unknown property: {}",
-                                property
-                            )))
-                        }
-                    }
-                }
-            }
+    /// Returns the theme a parent of a themed element is looked up in.
+    ///
+    /// It is the element's theme if the parent is defined there, so a themed
+    /// element can extend the un-themed one of the same name.
+    pub(crate) fn parent_theme<'a>(
+        &self,
+        theme: Option<&'a str>,
+        name: &str,
+        parent: &str,
+    ) -> Option<&'a str> {
+        theme.filter(|&theme| parent != name && self.element(Some(theme), parent).is_some())
+    }
+
+    /// Looks up an element as parsed, in a theme or among the un-themed ones.
+    pub(crate) fn element(&self, theme: Option<&str>, name: &str) -> Option<&Style> {
+        match theme {
+            Some(theme) => self.themes.get(theme)?.get(name),
+            None => self.styles.get(name),
         }
+    }
 
-        // Handle the last style if exists
-        if let Some(name) = current_name {
-            if self.styles.contains_key(&name) {
-                return Err(ParseError::DuplicateElement(name));
+    /// Parses TCSS content and populates the style map.
+    pub fn parse(&mut self, content: &str) -> Result<(), ParseError> {
+        Parser::new(content).parse(self)
+    }
+
+    /// Parses TCSS content, skipping invalid properties and blocks.
+    ///
+    /// Every valid element is added to the style map; the returned vector
+    /// holds one error per problem found, in source order.
+    pub fn parse_recovering(&mut self, content: &str) -> Vec<ParseError> {
+        let mut parser = Parser::new(content).recovering();
+        // A recovering parser records errors instead of returning them
+        let _ = parser.parse(self);
+        parser.into_errors()
+    }
+
+    /// Sets a variable that `var(--name)` references resolve to.
+    ///
+    /// Values set here take precedence over `@var` / `--name` definitions in
+    /// sheets, so call it before parsing to inject e.g. brand colours.
+    pub fn set_var(&mut self, name: &str, value: impl Into<String>) {
+        let name = name.strip_prefix("--").unwrap_or(name);
+        self.variable_overrides
+            .insert(name.to_string(), value.into());
+    }
+
+    /// Returns the value of a variable, accepting both `name` and `--name`.
+    pub fn var(&self, name: &str) -> Option<&str> {
+        let name = name.strip_prefix("--").unwrap_or(name);
+        self.variable_overrides
+            .get(name)
+            .or_else(|| self.variables.get(name))
+            .map(String::as_str)
+    }
+
+    /// Serializes all variables and styles back into TCSS.
+    ///
+    /// Variables, elements and themes are sorted by name, so the output is
+    /// deterministic and can be diffed. `@when` blocks keep their order,
+    /// since later blocks override earlier ones. The result parses back into
+    /// an equivalent Termio.
+    pub fn to_tcss(&self) -> String {
+        self.to_string()
+    }
+
+    /// Adds or replaces a style under the given name, including any
+    /// `extends` clause of the replaced one.
+    pub fn add_style(&mut self, name: &str, style: Style) {
+        self.parents.remove(&(None, name.to_string()));
+        self.styles.insert(name.to_string(), style);
+    }
+
+    /// Layers the elements, themes and variables of `other` on top of this
+    /// Termio, e.g. user overrides on top of app defaults.
+    ///
+    /// `policy` decides what happens to elements defined in both. `@when`
+    /// blocks of `other` are appended, so they win over existing ones.
+    /// Settings like capabilities and the active theme are kept.
+    pub fn merge(&mut self, other: Termio, policy: MergePolicy) -> Result<(), MergeError> {
+        if policy == MergePolicy::Error {
+            let mut duplicates = other
+                .styles
+                .keys()
+                .filter(|name| self.styles.contains_key(*name))
+                .map(|name| (None, name))
+                .chain(other.themes.iter().flat_map(|(theme, styles)| {
+                    styles
+                        .keys()
+                        .filter(|name| self.element(Some(theme), name).is_some())
+                        .map(move |name| (Some(theme), name))
+                }))
+                .collect::<Vec<_>>();
+            // Report the same duplicate regardless of hash map order
+            duplicates.sort();
+            if let Some((theme, name)) = duplicates.first() {
+                return Err(MergeError::DuplicateElement {
+                    name: name.to_string(),
+                    theme: theme.cloned(),
+                });
             }
-            self.styles.insert(name, current_style.unwrap_or_default());
         }
 
+        // Replaced elements lose their `extends` clause, cascaded ones keep it
+        // unless the merged sheet declares one
+        if policy == MergePolicy::Replace {
+            let replaced: Vec<_> = other
+                .styles
+                .keys()
+                .map(|name| (None, name.clone()))
+                .chain(other.themes.iter().flat_map(|(theme, styles)| {
+                    styles
+                        .keys()
+                        .map(|name| (Some(theme.clone()), name.clone()))
+                }))
+                .collect();
+            for key in replaced {
+                self.parents.remove(&key);
+            }
+        }
+        self.parents.extend(other.parents);
+        merge_styles(&mut self.styles, other.styles, policy);
+        for (theme, styles) in other.themes {
+            merge_styles(self.themes.entry(theme).or_default(), styles, policy);
+        }
+        self.conditional.extend(other.conditional);
+        self.variables.extend(other.variables);
+        self.palette.extend(&other.palette);
+        self.palette_overrides.extend(&other.palette_overrides);
+        self.variable_overrides.extend(other.variable_overrides);
+        for source in other.sources {
+            self.add_source(&source);
+        }
         Ok(())
     }
+}
 
-    /// Parses a decoration string into a vector of decorations
-    fn parse_decoration(&self, value: &str) -> Result<Vec<Decoration>, ParseError> {
-        value
-            .split_whitespace()
-            .map(|d| Decoration::from_str(d).map_err(|e| ParseError::InvalidSyntax(e.to_string())))
-            .collect()
+/// Merges `other` into `styles`; conflicts were already ruled out for `Error`.
+fn merge_styles(
+    styles: &mut HashMap<String, Style>,
+    other: HashMap<String, Style>,
+    policy: MergePolicy,
+) {
+    for (name, style) in other {
+        let style = match (policy, styles.get(&name)) {
+            (MergePolicy::Cascade, Some(existing)) => existing.merge(&style),
+            _ => style,
+        };
+        styles.insert(name, style);
+    }
+}
+
+impl fmt::Display for Termio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut variables: BTreeMap<&str, &str> = BTreeMap::new();
+        for (name, value) in self.variables.iter().chain(&self.variable_overrides) {
+            variables.insert(name, value);
+        }
+        let mut blocks = Vec::new();
+        if !variables.is_empty() {
+            let defs: Vec<String> = variables
+                .iter()
+                .map(|(name, value)| format!("@var {}: {};\n", name, value))
+                .collect();
+            blocks.push(defs.concat());
+        }
+        let palette = self.palette();
+        if !palette.is_empty() {
+            blocks.push(palette.to_string());
+        }
+
+        let styles: BTreeMap<&String, &Style> = self.styles.iter().collect();
+        for (name, style) in styles {
+            blocks.push(element_block(name, style, self.parents_of(None, name), ""));
+        }
+        let themes: BTreeMap<&String, &HashMap<String, Style>> = self.themes.iter().collect();
+        for (theme, styles) in themes {
+            let styles: BTreeMap<&String, &Style> = styles.iter().collect();
+            let elements: Vec<String> = styles
+                .iter()
+                .map(|(name, style)| {
+                    let parents = self.parents_of(Some(theme), name);
+                    element_block(name, style, parents, "    ")
+                })
+                .collect();
+            blocks.push(format!(
+                "@theme {} {{\n{}}}\n",
+                quote(theme),
+                elements.join("\n")
+            ));
+        }
+        for conditional in &self.conditional {
+            let block = match &conditional.theme {
+                Some(theme) => format!(
+                    "@theme {} {{\n    @when {} {{\n{}    }}\n}}\n",
+                    quote(theme),
+                    conditional.condition,
+                    element_block(&conditional.name, &conditional.style, None, "        ")
+                ),
+                None => format!(
+                    "@when {} {{\n{}}}\n",
+                    conditional.condition,
+                    element_block(&conditional.name, &conditional.style, None, "    ")
+                ),
+            };
+            blocks.push(block);
+        }
+
+        write!(f, "{}", blocks.join("\n"))
     }
+}
 
-    /// Parses a border style string into a BorderStyle
-    fn parse_border_style(&self, value: &str) -> Result<BorderStyle, ParseError> {
-        BorderStyle::from_str(value).map_err(|e| ParseError::InvalidSyntax(e.to_string()))
+/// Formats a single `@element` block with its `extends` clause, indented by `indent`.
+fn element_block(name: &str, style: &Style, parents: Option<&Vec<String>>, indent: &str) -> String {
+    let extends = match parents {
+        Some(parents) => {
+            let quoted: Vec<String> = parents.iter().map(|p| quote(p)).collect();
+            format!(" extends {}", quoted.join(", "))
+        }
+        None => String::new(),
+    };
+    let mut block = format!("{}@element {}{} {{\n", indent, quote(name), extends);
+    for declaration in style.declarations() {
+        block.push_str(&format!("{}    {};\n", indent, declaration));
     }
+    block.push_str(&format!("{}}}\n", indent));
+    block
+}
 
-    pub fn add_style(&mut self, name: &str, style: Style) {
-        self.styles.insert(name.to_string(), style);
+impl Default for Termio {
+    fn default() -> Self {
+        Termio::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeError, MergePolicy, Termio};
+    use crate::border::BorderStyle;
+    use crate::capabilities::Capabilities;
+    use crate::color::Color;
+    use crate::contrast::ContrastLevel;
+    use crate::decoration::Decoration;
+    use crate::vision::ColorBlindness;
+
+    #[test]
+    fn test_to_tcss_round_trip() {
+        let mut tcss = tcss! {
+            "zeta" => {
+                fg: Color::Rgb(255, 128, 0),
+                decoration: vec![Decoration::Bold, Decoration::DoubleUnderline],
+                padding: 1,
+                border_style: BorderStyle::Rounded
+            },
+            "alpha" => {
+                bg: Color::Code(236),
+                padding_left: 2,
+                margin_top: 1
+            }
+        };
+        tcss.parse(
+            "@var accent: i-cyan;\n\
+             @element \"beta\" {\n    color: var(--accent);\n    padding: 1 2;\n    margin: 1 2 3 4;\n}\n\
+             @when width < 80 {\n    @element \"beta\" {\n        padding: 0;\n    }\n}\n\
+             @theme \"dark\" {\n    @element \"beta\" {\n        color: white;\n    }\n\
+             @when no-color {\n        @element \"alpha\" {\n            margin: 0;\n        }\n    }\n}\n",
+        )
+        .unwrap();
+
+        let text = tcss.to_tcss();
+        assert_eq!(
+            text,
+            "@var accent: i-cyan;\n\
+             \n\
+             @element \"alpha\" {\n    background: 236;\n    padding-left: 2;\n    margin-top: 1;\n}\n\
+             \n\
+             @element \"beta\" {\n    color: i-cyan;\n    padding: 1 2;\n    margin: 1 2 3 4;\n}\n\
+             \n\
+             @element \"zeta\" {\n    color: rgb(255, 128, 0);\n    decoration: bold double-underline;\n    \
+             padding: 1;\n    border-style: rounded;\n}\n\
+             \n\
+             @theme \"dark\" {\n    @element \"beta\" {\n        color: white;\n    }\n}\n\
+             \n\
+             @when width < 80 {\n    @element \"beta\" {\n        padding: 0;\n    }\n}\n\
+             \n\
+             @theme \"dark\" {\n    @when no-color {\n        @element \"alpha\" {\n            \
+             margin: 0;\n        }\n    }\n}\n"
+        );
+
+        let mut reparsed = Termio::new();
+        reparsed.parse(&text).unwrap();
+        assert_eq!(reparsed.to_tcss(), text);
+    }
+
+    #[test]
+    fn test_to_tcss_escapes_strings() {
+        use crate::border::BorderChars;
+        use crate::gradient::Gradient;
+        use crate::style::Style;
+
+        let mut tcss = Termio::new();
+        let mut style = Style::new()
+            .fg(Color::Red)
+            .fg_gradient(Gradient::horizontal(vec![Color::Red, Color::Blue]))
+            .link("https://x/?q=\"{text}\"\\");
+        style.border_chars = Some(BorderChars::new('"', '\\', '|'));
+        tcss.add_style("say \"hi\"", style);
+        tcss.parse(
+            "@theme \"dark \\\"x\\\"\" {\n    @element \"a\\\\b\" extends \"a\\\\b\\\"\" {\n    }\n    \
+             @element \"a\\\\b\\\"\" {\n        color: blue;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let text = tcss.to_tcss();
+        assert!(text.contains("@element \"say \\\"hi\\\"\" {"));
+        assert!(text.contains("link: \"https://x/?q=\\\"{text}\\\"\\\\\";"));
+        assert!(text.contains("@theme \"dark \\\"x\\\"\" {"));
+        assert!(text.contains("@element \"a\\\\b\" extends \"a\\\\b\\\"\" {"));
+        // Only the gradient is written, since it replaces the color
+        assert_eq!(text.matches("color: ").count(), 2);
+
+        let mut reparsed = Termio::new();
+        reparsed.parse(&text).unwrap();
+        assert_eq!(reparsed.to_tcss(), text);
+        let said = reparsed.get_style("say \"hi\"").unwrap();
+        assert_eq!(said.link.as_deref(), Some("https://x/?q=\"{text}\"\\"));
+        assert_eq!(said.border_chars, Some(BorderChars::new('"', '\\', '|')));
+        assert!(said.fg_gradient.is_some());
+        reparsed.set_theme("dark \"x\"");
+        assert_eq!(reparsed.get_style("a\\b").unwrap().fg, Some(Color::Blue));
+    }
+
+    #[test]
+    fn test_inheritance_is_resolved_on_lookup() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"base\" {\n    color: white;\n}\n\
+             @element \"title\" extends \"base\" {\n    decoration: bold;\n}\n",
+        )
+        .unwrap();
+        let text = tcss.to_tcss();
+        assert!(text.contains("@element \"title\" extends \"base\" {\n    decoration: bold;\n}"));
+        let mut reparsed = Termio::new();
+        reparsed.parse(&text).unwrap();
+        assert_eq!(reparsed.to_tcss(), text);
+
+        // Changes to a parent reach existing children
+        tcss.add_style("base", style! { fg: Color::Cyan });
+        assert_eq!(tcss.get_style("title").unwrap().fg, Some(Color::Cyan));
+        let mut other = Termio::new();
+        other
+            .parse("@element \"base\" {\n    background: black;\n}\n")
+            .unwrap();
+        tcss.merge(other, MergePolicy::Cascade).unwrap();
+        let title = tcss.get_style("title").unwrap();
+        assert_eq!(
+            (title.fg, title.bg),
+            (Some(Color::Cyan), Some(Color::Black))
+        );
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let defaults = || {
+            let mut tcss = Termio::new();
+            tcss.parse("@element \"a\" {\n    color: red;\n    padding: 1;\n}\n")
+                .unwrap();
+            tcss
+        };
+        let overrides = || {
+            let mut tcss = Termio::new();
+            tcss.parse("@element \"a\" {\n    color: blue;\n}\n@element \"b\" {\n}\n")
+                .unwrap();
+            tcss
+        };
+
+        let mut tcss = defaults();
+        let err = tcss.merge(overrides(), MergePolicy::Error).unwrap_err();
+        assert_eq!(
+            err,
+            MergeError::DuplicateElement {
+                name: "a".to_string(),
+                theme: None
+            }
+        );
+        assert!(tcss.get_style("b").is_none());
+
+        let mut tcss = defaults();
+        tcss.merge(overrides(), MergePolicy::Replace).unwrap();
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!((a.fg, a.padding), (Some(Color::Blue), None));
+
+        let mut tcss = defaults();
+        tcss.merge(overrides(), MergePolicy::Cascade).unwrap();
+        let a = tcss.get_style("a").unwrap();
+        assert_eq!((a.fg, a.padding), (Some(Color::Blue), Some(1)));
+        assert!(tcss.get_style("b").is_some());
+    }
+
+    #[test]
+    fn test_lint_contrast() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"faint\" {\n    color: #aaaaaa;\n    background: #ffffff;\n}\n\
+             @element \"fine\" {\n    color: #000000;\n    background: #ffffff;\n}\n\
+             @theme \"dark\" {\n    @element \"fine\" {\n        color: #222222;\n        background: #000000;\n    }\n}\n\
+             @when width < 80 {\n    @element \"narrow\" {\n        color: #bbbbbb;\n        background: #ffffff;\n    }\n}\n\
+             @theme \"light\" {\n    @when width < 80 {\n        @element \"fine\" {\n            padding: 0;\n        }\n    }\n}\n",
+        )
+        .unwrap();
+
+        let names = |tcss: &Termio| -> Vec<String> {
+            tcss.lint_contrast(ContrastLevel::Aa)
+                .into_iter()
+                .map(|issue| issue.element.unwrap())
+                .collect()
+        };
+        assert_eq!(names(&tcss), ["faint"]);
+        assert_eq!(tcss.themes(), ["dark", "light"]);
+        // Elements only defined in `@when` blocks are linted while they match
+        tcss.set_capabilities(Capabilities::default().size(60, 20));
+        assert_eq!(names(&tcss), ["faint", "narrow"]);
+        tcss.set_capabilities(Capabilities::default());
+        tcss.set_theme("dark");
+        assert_eq!(names(&tcss), ["faint", "fine"]);
+
+        let tcss = tcss.with_readable_colors(ContrastLevel::Aa);
+        let fine = tcss.get_style("fine").unwrap();
+        assert!(fine.check_contrast(ContrastLevel::Aa).is_ok());
+        assert_eq!(tcss.lint_contrast(ContrastLevel::Aa).len(), 2);
+    }
+
+    #[test]
+    fn test_color_blindness() {
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@element \"status\" {\n    color: rgb(200, 0, 0);\n    background: rgb(0, 150, 0);\n}\n\
+             @element \"plain\" {\n    color: white;\n    background: black;\n}\n",
+        )
+        .unwrap();
+
+        let conflicts = tcss.lint_color_blindness(ColorBlindness::Deuteranopia);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].elements, ("status".into(), "status".into()));
+        assert_eq!(conflicts[0].properties, ("color", "background"));
+        assert!(tcss
+            .lint_color_blindness(ColorBlindness::Tritanopia)
+            .is_empty());
+
+        let tcss = tcss.with_simulation(Some(ColorBlindness::Protanopia));
+        assert_eq!(
+            tcss.get_style("status").unwrap().fg,
+            Some(Color::Rgb(84, 73, 0))
+        );
+    }
+
+    #[test]
+    fn test_color_blindness_across_elements() {
+        use crate::palette::Palette;
+
+        let mut tcss = Termio::new();
+        tcss.parse(
+            "@palette {\n    red: rgb(200, 0, 0);\n    green: rgb(0, 150, 0);\n}\n\
+             @element \"error\" {\n    color: red;\n}\n\
+             @element \"success\" {\n    color: green;\n}\n\
+             @element \"warning\" {\n    color: yellow;\n}\n",
+        )
+        .unwrap();
+        let tcss = tcss.with_palette_remapping(true);
+
+        let conflicts = tcss.lint_color_blindness(ColorBlindness::Deuteranopia);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].elements, ("error".into(), "success".into()));
+        assert_eq!(
+            conflicts[0].to_string(),
+            "@element \"error\" color rgb(200, 0, 0) and @element \"success\" color \
+             rgb(0, 150, 0) are hard to tell apart with deuteranopia"
+        );
+
+        // The safe palette keeps every basic color apart
+        let tcss = tcss.with_palette(Palette::okabe_ito());
+        for kind in ColorBlindness::ALL {
+            assert!(tcss.lint_color_blindness(kind).is_empty());
+        }
     }
 }
@@ -0,0 +1,206 @@
+//! Color vision deficiency simulation.
+//!
+//! Colors are transformed with the matrices of Machado, Oliveira and
+//! Fernandes (2009) at full severity, in linear RGB.
+
+use crate::color::{delta_e, Color};
+use std::fmt;
+
+/// A kind of dichromatic color blindness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBlindness {
+    Protanopia,   // No red cones
+    Deuteranopia, // No green cones
+    Tritanopia,   // No blue cones
+}
+
+impl ColorBlindness {
+    /// All kinds, for linting against each of them.
+    pub const ALL: [ColorBlindness; 3] = [
+        ColorBlindness::Protanopia,
+        ColorBlindness::Deuteranopia,
+        ColorBlindness::Tritanopia,
+    ];
+
+    fn matrix(&self) -> [[f64; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.304900],
+            ],
+        }
+    }
+}
+
+impl fmt::Display for ColorBlindness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ColorBlindness::Protanopia => "protanopia",
+            ColorBlindness::Deuteranopia => "deuteranopia",
+            ColorBlindness::Tritanopia => "tritanopia",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Color {
+    /// Returns the color as seen with the given color blindness.
+    pub fn simulate(&self, kind: ColorBlindness) -> Color {
+        let (r, g, b) = self.to_rgb();
+        let linear = [to_linear(r), to_linear(g), to_linear(b)];
+        let [r, g, b] = kind
+            .matrix()
+            .map(|row| from_linear(row.iter().zip(linear).map(|(m, v)| m * v).sum()));
+        Color::Rgb(r, g, b)
+    }
+}
+
+/// Two colors that look alike with a color blindness, either of one element
+/// or the foreground colors of two elements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorConflict {
+    /// The elements the colors belong to, the same one twice within an element
+    pub elements: (String, String),
+    pub kind: ColorBlindness,
+    /// The properties the colors are set by, e.g. `color` and `background`
+    pub properties: (&'static str, &'static str),
+    pub colors: (Color, Color),
+    /// Perceptual distance (CIE76 delta E) of the simulated colors
+    pub distance: f64,
+}
+
+impl fmt::Display for ColorConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = &self.elements;
+        if first == second {
+            write!(f, "@element \"{}\": ", first)?;
+        } else {
+            write!(f, "@element \"{}\" ", first)?;
+        }
+        write!(f, "{} {} and ", self.properties.0, self.colors.0)?;
+        if first != second {
+            write!(f, "@element \"{}\" ", second)?;
+        }
+        write!(
+            f,
+            "{} {} are hard to tell apart with {}",
+            self.properties.1, self.colors.1, self.kind
+        )
+    }
+}
+
+/// Colors closer than this are considered indistinguishable.
+pub(crate) const MIN_DISTANCE: f64 = 10.0;
+
+/// Finds the pairs of `colors` that are distinguishable normally but not
+/// with `kind`.
+pub(crate) fn conflicts(
+    element: &str,
+    kind: ColorBlindness,
+    colors: &[(&'static str, Color)],
+) -> Vec<ColorConflict> {
+    let mut conflicts = Vec::new();
+    for (i, &(first, a)) in colors.iter().enumerate() {
+        for &(second, b) in &colors[i + 1..] {
+            if let Some(distance) = conflict_distance(kind, a, b) {
+                conflicts.push(ColorConflict {
+                    elements: (element.to_string(), element.to_string()),
+                    kind,
+                    properties: (first, second),
+                    colors: (a, b),
+                    distance,
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Finds the pairs of elements whose foreground colors are distinguishable
+/// normally but not with `kind`.
+pub(crate) fn element_conflicts(
+    kind: ColorBlindness,
+    colors: &[(&str, Color)],
+) -> Vec<ColorConflict> {
+    let mut conflicts = Vec::new();
+    for (i, &(first, a)) in colors.iter().enumerate() {
+        for &(second, b) in &colors[i + 1..] {
+            if let Some(distance) = conflict_distance(kind, a, b) {
+                conflicts.push(ColorConflict {
+                    elements: (first.to_string(), second.to_string()),
+                    kind,
+                    properties: ("color", "color"),
+                    colors: (a, b),
+                    distance,
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Returns the distance of `a` and `b` as seen with `kind`, if they are
+/// distinguishable normally but not with it.
+fn conflict_distance(kind: ColorBlindness, a: Color, b: Color) -> Option<f64> {
+    let distance = delta_e(a.simulate(kind), b.simulate(kind));
+    (distance < MIN_DISTANCE && delta_e(a, b) >= MIN_DISTANCE).then_some(distance)
+}
+
+fn to_linear(v: u8) -> f64 {
+    let v = v as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(v: f64) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let v = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (v * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{conflicts, ColorBlindness};
+    use crate::color::Color;
+
+    #[test]
+    fn test_simulate() {
+        let white = Color::Rgb(255, 255, 255);
+        for kind in ColorBlindness::ALL {
+            assert_eq!(white.simulate(kind), white);
+        }
+        let red = Color::Rgb(200, 0, 0);
+        let green = Color::Rgb(0, 150, 0);
+        assert_eq!(
+            red.simulate(ColorBlindness::Protanopia),
+            Color::Rgb(84, 73, 0)
+        );
+
+        let colors = [("color", red), ("background", green)];
+        assert_eq!(conflicts("a", ColorBlindness::Tritanopia, &colors), []);
+        let found = conflicts("a", ColorBlindness::Deuteranopia, &colors);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].to_string(),
+            "@element \"a\": color rgb(200, 0, 0) and background rgb(0, 150, 0) are hard to tell apart with deuteranopia"
+        );
+    }
+}